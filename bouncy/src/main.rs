@@ -19,11 +19,20 @@ struct BouncyElement;
 #[derive(Component)]
 struct Ball;
 
+/// Which way to find colliding balls. Both report each touching pair
+/// once, so the counts can be compared directly.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum CollisionMode {
+    Naive,
+    #[default]
+    Quadtree,
+}
+
 #[derive(Resource, Default)]
 struct CollisionTime {
+    started: Option<std::time::Instant>,
     time: u128,
-    checks: u32,
-    fps: f64,
+    collisions: u32,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -31,30 +40,40 @@ fn main() -> anyhow::Result<()> {
     add_phase!(app, GamePhase, GamePhase::Bouncing,
         start => [setup ],
         run => [
-            warp_at_edge, collisions, show_performance,
-            continual_parallax, physics_clock,
-            sum_impulses, apply_velocity
+            warp_at_edge, start_collision_clock.before(PhysicsSet::Collide),
+            naive_collisions
+                .in_set(PhysicsSet::Collide)
+                .run_if(resource_equals(CollisionMode::Naive)),
+            bounce.after(PhysicsSet::Collide),
+            show_performance, continual_parallax
         ],
         exit => [cleanup::<BouncyElement>],
     );
 
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
-            title: "Naieve Collision".to_string(),
+            title: "Quadtree Collision".to_string(),
             resolution: bevy::window::WindowResolution::new(1024.0, 768.0),
             ..default()
         }),
         ..default()
     }))
     .add_plugins(FrameTimeDiagnosticsPlugin)
-    .add_event::<Impulse>()
-    .add_event::<PhysicsTick>()
+    .add_plugins(PhysicsPlugin::new([GamePhase::Bouncing]))
     .add_plugins(GameStatePlugin::new(
         GamePhase::MainMenu,
         GamePhase::Bouncing,
         GamePhase::GameOver,
     ))
     .add_plugins(RandomPlugin)
+    .add_plugins(CollisionPlugin::<Ball, Ball>::default())
+    // Only one way of finding collisions runs at a time, so the timing
+    // shown is for that way alone
+    .configure_sets(
+        Update,
+        CollisionSet::<Ball, Ball>::default()
+            .run_if(resource_exists_and_equals(CollisionMode::Quadtree)),
+    )
     .add_plugins(AssetManager::new().add_image("green_ball", "green_ball.png")?)
     .run();
 
@@ -72,7 +91,7 @@ fn spawn_bouncies(
         let position =
             Vec3::new(rng.range(-512.0..512.0), rng.range(-384.0..384.0), 0.0);
         let velocity =
-            Vec3::new(rng.range(-1.0..1.0), rng.range(-1.0..1.0), 0.0);
+            Vec3::new(rng.range(-30.0..30.0), rng.range(-30.0..30.0), 0.0);
         spawn_image!(
             assets,
             commands,
@@ -83,6 +102,11 @@ fn spawn_bouncies(
             &loaded_assets,
             BouncyElement,
             Velocity::new(velocity.x, velocity.y, velocity.z),
+            PhysicsInterpolation::default(),
+            // Bleeds off the speed each bounce adds, so a crowded
+            // screen doesn't end up with balls racing around
+            LinearDamping(0.5),
+            CircleCollider::new(4.0),
             Ball
         );
     }
@@ -94,8 +118,9 @@ fn setup(
     assets: Res<AssetStore>,
     loaded_assets: Res<LoadedAssets>,
 ) {
-    commands.spawn(Camera2d::default()).insert(BouncyElement);
+    commands.spawn(Camera2d).insert(BouncyElement);
     commands.insert_resource(CollisionTime::default());
+    commands.insert_resource(CollisionMode::default());
     spawn_bouncies(1, &mut commands, &mut rng, &assets, &loaded_assets);
 }
 
@@ -116,10 +141,12 @@ fn warp_at_edge(mut query: Query<&mut Transform, With<Ball>>) {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn show_performance(
     mut egui_context: egui::EguiContexts,
     diagnostics: Res<DiagnosticsStore>,
-    mut collision_time: ResMut<CollisionTime>,
+    collision_time: Res<CollisionTime>,
+    mut mode: ResMut<CollisionMode>,
     mut commands: Commands,
     mut rng: ResMut<RandomNumberGenerator>,
     assets: Res<AssetStore>,
@@ -131,7 +158,6 @@ fn show_performance(
         .get(&FrameTimeDiagnosticsPlugin::FPS)
         .and_then(|fps| fps.average())
         .unwrap();
-    collision_time.fps = fps;
     egui::egui::Window::new("Performance").show(egui_context.ctx_mut(), |ui| {
         let fps_text = format!("FPS: {fps:.1}");
         let color = match fps as u32 {
@@ -139,25 +165,24 @@ fn show_performance(
             30..=59 => Color32::GOLD,
             _ => Color32::GREEN,
         };
-        ui.colored_label(color, &fps_text);
+        ui.colored_label(color, fps_text);
         ui.colored_label(
             color,
-            &format!("Collision Time: {} ms", collision_time.time),
+            format!("Collision Time: {} ms", collision_time.time),
         );
-        ui.label(&format!("Collision Checks: {}", collision_time.checks));
-        ui.label(&format!("# Balls: {n_balls}"));
+        ui.label(format!("Collisions: {}", collision_time.collisions));
+        ui.label(format!("# Balls: {n_balls}"));
+        ui.label(format!("Mode: {:?}", *mode));
+        if ui.button("Switch Mode").clicked() {
+            *mode = match *mode {
+                CollisionMode::Naive => CollisionMode::Quadtree,
+                CollisionMode::Quadtree => CollisionMode::Naive,
+            };
+        }
         if ui.button("Add Ball").clicked() {
-            println!(
-                "{n_balls}, {}, {}, {:.0}",
-                collision_time.time, collision_time.checks, collision_time.fps
-            );
             spawn_bouncies(1, &mut commands, &mut rng, &assets, &loaded_assets);
         }
         if ui.button("Add 100 Balls").clicked() {
-            println!(
-                "{n_balls}, {}, {}, {:.0}",
-                collision_time.time, collision_time.checks, collision_time.fps
-            );
             spawn_bouncies(
                 100,
                 &mut commands,
@@ -167,10 +192,6 @@ fn show_performance(
             );
         }
         if ui.button("Add 1000 Balls").clicked() {
-            println!(
-                "{n_balls}, {}, {}, {:.0}",
-                collision_time.time, collision_time.checks, collision_time.fps
-            );
             spawn_bouncies(
                 1000,
                 &mut commands,
@@ -182,49 +203,75 @@ fn show_performance(
     });
 }
 
-fn bounce_on_collision(
-    entity: Entity,
+fn start_collision_clock(mut collision_time: ResMut<CollisionTime>) {
+    collision_time.started = Some(std::time::Instant::now());
+}
+
+fn bounce_pair(
+    entity_a: Entity,
     ball_a: Vec3,
+    entity_b: Entity,
     ball_b: Vec3,
     impulse: &mut EventWriter<Impulse>,
 ) {
-    let a_to_b = (ball_a - ball_b).normalize();
-    impulse.send(Impulse {
-        target: entity,
-        amount: a_to_b / 8.0,
-        absolute: false,
-    });
+    let a_to_b = (ball_a - ball_b).normalize_or_zero();
+    impulse.send(Impulse::add(entity_a, a_to_b * 3.75).with_source("bounce"));
+    impulse.send(Impulse::add(entity_b, -a_to_b * 3.75).with_source("bounce"));
 }
 
-fn collisions(
+fn naive_collisions(
     mut collision_time: ResMut<CollisionTime>,
     query: Query<(Entity, &Transform), With<Ball>>,
     mut impulse: EventWriter<Impulse>,
 ) {
-    // Start the clock
-    let now = std::time::Instant::now();
-
-    // Naïve Collision
+    // Naïve Collision, visiting each unordered pair once
     let mut n = 0;
-    for (entity_a, ball_a) in query.iter() {
-        query
-            .iter()
-            .filter(|(entity_b, _)| *entity_b != entity_a)
-            .filter(|(_, ball_b)| {
-                n += 1; // Count the collision check
-                ball_a.translation.distance(ball_b.translation) < 8.0
-            })
-            .for_each(|(_, ball_b)| {
-                bounce_on_collision(
-                    entity_a,
+    for [(entity_a, ball_a), (entity_b, ball_b)] in query.iter_combinations() {
+        if ball_a.translation.distance(ball_b.translation) <= 8.0 {
+            n += 1;
+            bounce_pair(
+                entity_a,
+                ball_a.translation,
+                entity_b,
+                ball_b.translation,
+                &mut impulse,
+            );
+        }
+    }
+    collision_time.collisions = n;
+}
+
+fn bounce(
+    mode: Res<CollisionMode>,
+    mut collision_time: ResMut<CollisionTime>,
+    mut collisions: EventReader<OnCollision<Ball, Ball>>,
+    query: Query<&Transform, With<Ball>>,
+    mut impulse: EventWriter<Impulse>,
+) {
+    if *mode == CollisionMode::Quadtree {
+        let mut n = 0;
+        for collision in collisions.read() {
+            n += 1;
+            if let Ok([ball_a, ball_b]) =
+                query.get_many([collision.entity_a, collision.entity_b])
+            {
+                bounce_pair(
+                    collision.entity_a,
                     ball_a.translation,
+                    collision.entity_b,
                     ball_b.translation,
                     &mut impulse,
                 );
-            });
+            }
+        }
+        collision_time.collisions = n;
+    } else {
+        // Drop any sent before switching to naive checks
+        collisions.clear();
     }
 
     // Store the time result
-    collision_time.time = now.elapsed().as_millis();
-    collision_time.checks = n;
+    if let Some(started) = collision_time.started.take() {
+        collision_time.time = started.elapsed().as_millis();
+    }
 }
@@ -25,11 +25,10 @@ fn main() -> anyhow::Result<()> {
     add_phase!(app, GamePhase, GamePhase::Flapping,
         start => [setup],
         run => [
-            flap, clamp, move_walls, hit_wall,
-            cycle_animations, continual_parallax,
-            physics_clock, sum_impulses,
-            apply_gravity, apply_velocity,
-            check_collisions::<Flappy, Obstacle> 
+            flap.before(PhysicsSet::Forces), tilt.before(PhysicsSet::Integrate),
+            clamp, move_walls,
+            hit_wall.after(PhysicsSet::Collide),
+            cycle_animations, continual_parallax
         ],
         exit => [cleanup::<FlappyElement>],
     );
@@ -43,6 +42,7 @@ fn main() -> anyhow::Result<()> {
         ..default()
     }))
     .add_plugins(RandomPlugin)
+    .add_plugins(PhysicsPlugin::new([GamePhase::Flapping]))
     .add_plugins(GameStatePlugin::new(
         GamePhase::MainMenu,
         GamePhase::Flapping,
@@ -62,6 +62,7 @@ fn main() -> anyhow::Result<()> {
                 4,
                 1,
             )?
+            .with_pixel_mask("flappy")
             .add_image("bg_static", "rocky-far-mountains.png")?
             .add_image("bg_far", "rocky-nowater-far.png")?
             .add_image("bg_mid", "rocky-nowater-mid.png")?
@@ -125,7 +126,7 @@ fn main() -> anyhow::Result<()> {
                 ]),
             ),
     )
-    .add_event::<OnCollision<Flappy, Obstacle>>()
+    .add_plugins(CollisionPlugin::<Flappy, Obstacle>::default())
     .run();
 
     Ok(())
@@ -137,7 +138,7 @@ fn setup(
     assets: Res<AssetStore>,
     loaded_assets: AssetResource,
 ) {
-    commands.spawn(Camera2d::default()).insert(FlappyElement);
+    commands.spawn(Camera2d).insert(FlappyElement);
     spawn_animated_sprite!(
         assets,
         commands,
@@ -149,10 +150,14 @@ fn setup(
         Flappy,
         FlappyElement,
         Velocity::default(),
+        AngularVelocity::default(),
+        PhysicsInterpolation::default(),
         ApplyGravity,
-        AxisAlignedBoundingBox::new(62.0, 65.0)
+        assets
+            .get_pixel_mask("flappy")
+            .expect("The loading menu builds the flappy mask"),
+        ContinuousCollision::default()
     );
-    commands.insert_resource(StaticQuadTree::new(Vec2::new(1024.0, 768.0), 4));
     build_wall(&mut commands, &assets, rng.range(-5..5), &loaded_assets);
     spawn_image!(
         assets,
@@ -250,7 +255,8 @@ fn build_wall(
                 &loaded_assets,
                 Obstacle,
                 FlappyElement,
-                Velocity::new(-4.0, 0.0, 0.0),
+                Velocity::new(-120.0, 0.0, 0.0),
+                PhysicsInterpolation::default(),
                 AxisAlignedBoundingBox::new(32.0, 32.0)
             );
         }
@@ -262,15 +268,25 @@ fn flap(
     mut query: Query<(Entity, &mut AnimationCycle)>,
     mut impulse: EventWriter<Impulse>,
 ) {
-    if keyboard.pressed(KeyCode::Space) {
-        if let Ok((flappy, mut animation)) = query.get_single_mut() {
-            impulse.send(Impulse {
-                target: flappy,
-                amount: Vec3::Y,
-                absolute: false,
-            });
-            animation.switch("Flapping");
-        }
+    if keyboard.pressed(KeyCode::Space)
+        && let Ok((flappy, mut animation)) = query.get_single_mut()
+    {
+        impulse.send(Impulse::add(flappy, Vec3::Y * 30.0).with_source("flap"));
+        animation.switch("Flapping");
+    }
+}
+
+fn tilt(
+    mut query: Query<
+        (&Velocity, &Transform, &mut AngularVelocity),
+        With<Flappy>,
+    >,
+) {
+    if let Ok((velocity, transform, mut angular)) = query.get_single_mut() {
+        // Nose up while climbing, nose down while falling
+        let target = (velocity.0.y * 0.002).clamp(-0.8, 0.5);
+        let (current, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+        angular.0 = (target - current) * 7.5;
     }
 }
 
@@ -310,14 +326,16 @@ fn move_walls(
 }
 
 fn hit_wall(
-    mut collisions: EventReader<OnCollision<Flappy, Obstacle>>,
+    mut collisions: EventReader<CollisionStarted<Flappy, Obstacle>>,
     mut state: ResMut<NextState<GamePhase>>,
     assets: Res<AssetStore>,
     loaded_assets: Res<LoadedAssets>,
     mut commands: Commands,
 ) {
-    for _collision in collisions.read() {
+    // Several wall blocks may be hit on the same frame; crash only once
+    if !collisions.is_empty() {
+        collisions.clear();
         assets.play("crash", &mut commands, &loaded_assets);
-        let _ = state.set(GamePhase::GameOver);
+        state.set(GamePhase::GameOver);
     }
 }
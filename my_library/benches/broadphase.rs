@@ -0,0 +1,53 @@
+use bevy::prelude::Vec2;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use my_library::*;
+
+fn scattered(count: usize) -> Vec<Rect2D> {
+    let mut rng = RandomNumberGenerator::seeded(count as u64);
+    // Keep the crowd about as dense at every size
+    let extent = (count as f32).sqrt() * 40.0;
+    (0..count)
+        .map(|_| {
+            let center = Vec2::new(
+                rng.range(-extent..extent),
+                rng.range(-extent..extent),
+            );
+            let half = Vec2::new(rng.range(2.0..16.0), rng.range(2.0..16.0));
+            Rect2D::new(center - half, center + half)
+        })
+        .collect()
+}
+
+fn bench_backend(c: &mut Criterion, name: &str, mut backend: impl Broadphase) {
+    let mut group = c.benchmark_group(name);
+    for count in [100, 1_000, 10_000] {
+        let bounds = scattered(count);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &bounds,
+            |b, bounds| {
+                let mut pairs = Vec::new();
+                b.iter(|| {
+                    backend.build(bounds);
+                    pairs.clear();
+                    backend.pairs(&mut pairs);
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    bench_backend(
+        c,
+        "quadtree",
+        QuadTreeBroadphase::new(StaticQuadTree::new(Vec2::splat(8_000.0), 6)),
+    );
+    bench_backend(c, "spatial_hash", SpatialHashBroadphase::new(32.0));
+    bench_backend(c, "sweep_and_prune", SweepAndPruneBroadphase::new());
+    bench_backend(c, "dynamic_quadtree", DynamicQuadTreeBroadphase::default());
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,85 @@
+use bevy::{
+    core::{TaskPoolOptions, TaskPoolPlugin, TaskPoolThreadAssignmentPolicy},
+    prelude::*,
+    tasks::ComputeTaskPool,
+};
+use criterion::{BenchmarkId, Criterion};
+use my_library::*;
+use std::process::Command;
+
+/// Set on the copies of this benchmark that each time one thread count.
+const THREADS_VAR: &str = "PARALLEL_BENCH_THREADS";
+
+#[derive(Component)]
+struct Ball;
+
+/// An app that checks a crowd of `count` balls for collisions with
+/// `threads` threads in its compute pool.
+fn crowd(count: usize, threads: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins(TaskPoolPlugin {
+        task_pool_options: TaskPoolOptions {
+            compute: TaskPoolThreadAssignmentPolicy {
+                min_threads: threads,
+                max_threads: threads,
+                percent: 1.0,
+            },
+            ..default()
+        },
+    })
+    .add_plugins(
+        CollisionPlugin::<Ball, Ball>::default()
+            .with_broadphase(SpatialHashBroadphase::new(16.0)),
+    );
+    assert_eq!(ComputeTaskPool::get().thread_num(), threads);
+
+    let mut rng = RandomNumberGenerator::seeded(count as u64);
+    // Keep the crowd about as dense at every size
+    let extent = (count as f32).sqrt() * 20.0;
+    for _ in 0..count {
+        app.world_mut().spawn((
+            Ball,
+            Transform::from_xyz(
+                rng.range(-extent..extent),
+                rng.range(-extent..extent),
+                0.0,
+            ),
+            CircleCollider::new(4.0),
+        ));
+    }
+    app
+}
+
+fn bench_threads(c: &mut Criterion, count: usize, threads: usize) {
+    let mut group = c.benchmark_group(format!("collisions_{count}"));
+    let mut app = crowd(count, threads);
+    group.bench_function(BenchmarkId::from_parameter(threads), |b| {
+        b.iter(|| app.update());
+    });
+    group.finish();
+}
+
+fn main() {
+    match std::env::var(THREADS_VAR) {
+        Ok(threads) => {
+            let threads = threads.parse().expect("A number of threads");
+            let mut criterion = Criterion::default().configure_from_args();
+            bench_threads(&mut criterion, 1_000, threads);
+            bench_threads(&mut criterion, 10_000, threads);
+            criterion.final_summary();
+        }
+        // Bevy's compute pool can only be set up once per process, so
+        // each thread count is timed by a fresh copy of this benchmark
+        Err(_) => {
+            let exe = std::env::current_exe().expect("The benchmark's path");
+            for threads in [1, 2, 4, 8] {
+                let status = Command::new(&exe)
+                    .args(std::env::args().skip(1))
+                    .env(THREADS_VAR, threads.to_string())
+                    .status()
+                    .expect("Unable to run the benchmark");
+                assert!(status.success(), "{threads} threads failed");
+            }
+        }
+    }
+}
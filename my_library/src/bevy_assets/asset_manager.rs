@@ -17,6 +17,7 @@ pub enum AssetType {
 #[derive(Resource, Clone)]
 pub struct AssetManager {
     pub(crate) asset_list: Vec<(String, String, AssetType)>,
+    pub(crate) pixel_masks: Vec<String>,
 }
 
 impl AssetManager {
@@ -34,6 +35,7 @@ impl AssetManager {
                     AssetType::Image,
                 ),
             ],
+            pixel_masks: vec![],
         }
     }
 
@@ -99,6 +101,22 @@ impl AssetManager {
         ));
         Ok(self)
     }
+
+    /// Builds a [`PixelMask`](crate::PixelMask) for the image or sprite
+    /// sheet added as `tag` once it has loaded, with one frame per
+    /// sprite for sheets. Fetch it with
+    /// [`AssetStore::get_pixel_mask`]. Loading panics if the mask can't
+    /// be built, for example if `tag` isn't an image or sprite sheet.
+    pub fn with_pixel_mask<S: ToString>(mut self, tag: S) -> Self {
+        self.pixel_masks.push(tag.to_string());
+        self
+    }
+}
+
+impl Default for AssetManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Plugin for AssetManager {
@@ -116,6 +134,8 @@ pub(crate) fn setup_asset_store(
         asset_index: bevy::utils::HashMap::new(),
         atlases_to_build: vec![],
         atlases: bevy::utils::HashMap::new(),
+        masks_to_build: asset_resource.pixel_masks.clone(),
+        pixel_masks: bevy::utils::HashMap::new(),
     };
     asset_resource
         .asset_list
@@ -1,3 +1,4 @@
+use crate::PixelMask;
 use bevy::{
     asset::{Asset, LoadedUntypedAsset},
     prelude::*,
@@ -13,6 +14,8 @@ pub struct AssetStore {
     pub(crate) atlases_to_build: Vec<FutureAtlas>,
     pub(crate) atlases:
         HashMap<String, (Handle<Image>, Handle<TextureAtlasLayout>)>,
+    pub(crate) masks_to_build: Vec<String>,
+    pub(crate) pixel_masks: HashMap<String, PixelMask>,
 }
 
 impl AssetStore {
@@ -41,6 +44,14 @@ impl AssetStore {
         None
     }
 
+    /// The mask requested with
+    /// [`AssetManager::with_pixel_mask`](crate::AssetManager::with_pixel_mask),
+    /// once loading has finished. Only `None` for a tag that wasn't
+    /// requested, or before loading is done.
+    pub fn get_pixel_mask(&self, index: &str) -> Option<PixelMask> {
+        self.pixel_masks.get(index).cloned()
+    }
+
     pub fn play(
         &self,
         sound_name: &str,
@@ -1,7 +1,9 @@
 use std::time::Duration;
 
 use crate::bevy_assets::setup_asset_store;
-use crate::{AssetManager, AssetStore, MenuResource, egui::egui::Window};
+use crate::{
+    AssetManager, AssetStore, MenuResource, PixelMask, egui::egui::Window,
+};
 use bevy::state::state::FreelyMutableState;
 use bevy::{asset::LoadedUntypedAsset, prelude::*};
 use bevy_egui::EguiContexts;
@@ -53,6 +55,32 @@ fn load_atlases(
     }
 }
 
+fn build_pixel_masks(
+    store: &mut AssetStore,
+    images: &Assets<Image>,
+    texture_atlases: &Assets<TextureAtlasLayout>,
+    loaded_assets: &LoadedAssets,
+) {
+    for tag in std::mem::take(&mut store.masks_to_build) {
+        let mask = if let Some((img, atlas)) = store.get_atlas_handle(&tag) {
+            images.get(&img).zip(texture_atlases.get(&atlas)).and_then(
+                |(image, layout)| PixelMask::from_atlas(image, layout),
+            )
+        } else {
+            store
+                .get_handle::<Image>(&tag, loaded_assets)
+                .and_then(|img| images.get(&img))
+                .and_then(PixelMask::from_image)
+        };
+        // Colliders would silently go missing without it
+        let Some(mask) = mask else {
+            panic!("Unable to build a pixel mask for [{tag}]");
+        };
+        store.pixel_masks.insert(tag, mask);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn run<T>(
     asset_server: Res<AssetServer>,
     mut to_load: ResMut<AssetsToLoad>,
@@ -61,20 +89,27 @@ pub(crate) fn run<T>(
     menu_info: Res<MenuResource<T>>,
     mut store: ResMut<AssetStore>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    images: Res<Assets<Image>>,
     loaded_assets: Res<LoadedAssets>,
     time: Res<Time>,
 ) where
     T: States + FromWorld + FreelyMutableState,
 {
     let can_skip = time.elapsed() > Duration::from_secs(3);
-    to_load
-        .0
-        .retain(|handle| match asset_server.get_load_state(handle.id()) {
-            Some(bevy::asset::LoadState::Loaded) => false,
-            _ => true,
-        });
+    to_load.0.retain(|handle| {
+        !matches!(
+            asset_server.get_load_state(handle.id()),
+            Some(bevy::asset::LoadState::Loaded)
+        )
+    });
     if to_load.0.is_empty() && can_skip {
         load_atlases(&mut store, &mut texture_atlases, &loaded_assets);
+        build_pixel_masks(
+            &mut store,
+            &images,
+            &texture_atlases,
+            &loaded_assets,
+        );
         state.set(menu_info.menu_state.clone());
     }
     Window::new("Loading, Please Wait").show(egui_context.ctx_mut(), |ui| {
@@ -38,12 +38,12 @@ impl PerFrameAnimation {
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct Animations(HashMap<String, PerFrameAnimation>);
 
 impl Animations {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self::default()
     }
     pub fn with_animation<S: ToString>(
         mut self,
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use super::rect2d::Rect2D;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug)]
 pub struct AxisAlignedBoundingBox {
   half_size: Vec2,
 }
@@ -14,6 +14,10 @@ impl AxisAlignedBoundingBox {
     }
   }
 
+  pub fn size(&self) -> Vec2 {
+    self.half_size * 2.0
+  }
+
   pub fn as_rect(&self, translate: Vec2) -> Rect2D {
     Rect2D::new(
       Vec2::new(translate.x - self.half_size.x, 
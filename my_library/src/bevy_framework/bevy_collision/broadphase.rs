@@ -0,0 +1,752 @@
+use bevy::{
+  math::I64Vec2,
+  prelude::*,
+  tasks::{ParallelSlice, TaskPool},
+  utils::HashMap,
+};
+use super::{rect2d::Rect2D, DynamicQuadTree, StaticQuadTree};
+use std::marker::PhantomData;
+
+/// Finds which colliders are close enough to be worth testing exactly.
+/// Each backend suits a different spread of entities; pick one with
+/// `with_broadphase` on a collision plugin.
+///
+/// Colliders are identified by their position in the slice given to
+/// [`build`](Broadphase::build). Results only include colliders whose
+/// bounds really touch, and never repeat an index or a pair.
+pub trait Broadphase: Send + Sync + 'static {
+  /// Replaces everything stored with `bounds`.
+  fn build(&mut self, bounds: &[Rect2D]);
+
+  /// Like [`build`](Broadphase::build), but spreads what work it can
+  /// across `pool`. Stores just what `build` would.
+  fn build_on(&mut self, bounds: &[Rect2D], _pool: &TaskPool) {
+    self.build(bounds);
+  }
+
+  /// Adds the index of every stored collider touching `area`.
+  fn query(&self, area: &Rect2D, found: &mut Vec<usize>);
+
+  /// Adds every pair of stored colliders that touch each other, smaller
+  /// index first.
+  fn pairs(&self, found: &mut Vec<(usize, usize)>);
+
+  /// Moves whatever area the backend is laid out over so it is centred
+  /// on `center`. [`follow_camera`] calls it as the camera scrolls.
+  /// Backends that don't depend on where things are ignore it.
+  fn recenter(&mut self, _center: Vec2) {}
+}
+
+/// The broadphase used by one collision plugin. `M` tells the plugins
+/// apart.
+#[derive(Resource)]
+pub struct CollisionBroadphase<M> {
+  backend: Box<dyn Broadphase>,
+  marker: PhantomData<fn() -> M>,
+}
+
+impl<M> CollisionBroadphase<M> {
+  pub fn new(backend: impl Broadphase) -> Self {
+    Self {
+      backend: Box::new(backend),
+      marker: PhantomData,
+    }
+  }
+
+  pub fn backend(&self) -> &dyn Broadphase {
+    self.backend.as_ref()
+  }
+
+  pub fn backend_mut(&mut self) -> &mut dyn Broadphase {
+    self.backend.as_mut()
+  }
+}
+
+/// Files colliders under the smallest [`StaticQuadTree`] node that
+/// holds them. Good when entities are spread across a fixed screen.
+#[derive(Clone, Debug)]
+pub struct QuadTreeBroadphase {
+  tree: StaticQuadTree,
+  bounds: Vec<Rect2D>,
+  nodes: HashMap<usize, Vec<usize>>,
+}
+
+impl QuadTreeBroadphase {
+  pub fn new(tree: StaticQuadTree) -> Self {
+    Self {
+      tree,
+      bounds: Vec::new(),
+      nodes: HashMap::new(),
+    }
+  }
+}
+
+impl Default for QuadTreeBroadphase {
+  /// A 1024x768 screen, split four levels deep.
+  fn default() -> Self {
+    Self::new(StaticQuadTree::new(Vec2::new(1024.0, 768.0), 4))
+  }
+}
+
+impl QuadTreeBroadphase {
+  /// Stores `bounds`, each under the node at the same place in `nodes`.
+  fn file(&mut self, bounds: &[Rect2D], nodes: Vec<usize>) {
+    self.bounds = bounds.to_vec();
+    self.nodes.clear();
+    for (index, node) in nodes.into_iter().enumerate() {
+      self.nodes.entry(node).or_default().push(index);
+    }
+  }
+}
+
+impl Broadphase for QuadTreeBroadphase {
+  fn build(&mut self, bounds: &[Rect2D]) {
+    let nodes = bounds
+      .iter()
+      .map(|rect| self.tree.smallest_node(rect))
+      .collect();
+    self.file(bounds, nodes);
+  }
+
+  fn build_on(&mut self, bounds: &[Rect2D], pool: &TaskPool) {
+    // Walking the tree is the costly part; filing is just a push
+    let nodes = par_map(pool, bounds, |rect| self.tree.smallest_node(rect));
+    self.file(bounds, nodes);
+  }
+
+  fn query(&self, area: &Rect2D, found: &mut Vec<usize>) {
+    let mut nodes = self.tree.intersecting_nodes(area);
+    // Colliders outside the tree's bounds all live in the root
+    nodes.insert(0);
+    for node in nodes {
+      if let Some(contents) = self.nodes.get(&node) {
+        found.extend(
+          contents
+            .iter()
+            .filter(|index| self.bounds[**index].intersect(area)),
+        );
+      }
+    }
+  }
+
+  fn pairs(&self, found: &mut Vec<(usize, usize)>) {
+    let start = found.len();
+    let mut nearby = Vec::new();
+    for (index, rect) in self.bounds.iter().enumerate() {
+      nearby.clear();
+      self.query(rect, &mut nearby);
+      found.extend(
+        nearby
+          .iter()
+          .filter(|other| **other != index)
+          .map(|other| (index.min(*other), index.max(*other))),
+      );
+    }
+    // Each pair is usually found from both ends
+    found[start..].sort_unstable();
+    dedup_from(found, start);
+  }
+}
+
+/// Buckets colliders into a uniform grid of square cells. Good when
+/// entities are of similar size and spread over a large or scrolling
+/// world; choose a cell a little larger than a typical collider.
+///
+/// Colliders covering more than [`MAX_CELLS`](Self::MAX_CELLS) cells
+/// aren't bucketed; they are kept aside and tested against everything.
+#[derive(Clone, Debug)]
+pub struct SpatialHashBroadphase {
+  cell_size: f32,
+  bounds: Vec<Rect2D>,
+  cells: HashMap<IVec2, Vec<usize>>,
+  oversized: Vec<usize>,
+}
+
+impl SpatialHashBroadphase {
+  pub fn new(cell_size: f32) -> Self {
+    assert!(cell_size > 0.0, "Cell size must be positive");
+    Self {
+      cell_size,
+      bounds: Vec::new(),
+      cells: HashMap::new(),
+      oversized: Vec::new(),
+    }
+  }
+
+  /// The most cells one collider is bucketed into.
+  pub const MAX_CELLS: usize = 64;
+
+  fn cell_range(&self, rect: &Rect2D) -> (IVec2, IVec2) {
+    (
+      (rect.min() / self.cell_size).floor().as_ivec2(),
+      (rect.max() / self.cell_size).floor().as_ivec2(),
+    )
+  }
+
+  fn cell_count(min: IVec2, max: IVec2) -> usize {
+    // Far-off cells are saturated at the ends of i32, so widen first
+    let size = (max.as_i64vec2() - min.as_i64vec2() + I64Vec2::ONE)
+      .max(I64Vec2::ZERO);
+    size.x.saturating_mul(size.y) as usize
+  }
+}
+
+impl SpatialHashBroadphase {
+  /// Buckets `bounds`, numbering them from `first`, and lists the ones
+  /// too big to bucket.
+  fn bucket(
+    &self,
+    first: usize,
+    bounds: &[Rect2D],
+  ) -> (HashMap<IVec2, Vec<usize>>, Vec<usize>) {
+    let mut cells: HashMap<IVec2, Vec<usize>> = HashMap::new();
+    let mut oversized = Vec::new();
+    for (index, rect) in bounds.iter().enumerate() {
+      let (min, max) = self.cell_range(rect);
+      if Self::cell_count(min, max) > Self::MAX_CELLS {
+        oversized.push(first + index);
+        continue;
+      }
+      for y in min.y..=max.y {
+        for x in min.x..=max.x {
+          cells.entry(IVec2::new(x, y)).or_default().push(first + index);
+        }
+      }
+    }
+    (cells, oversized)
+  }
+}
+
+impl Broadphase for SpatialHashBroadphase {
+  fn build(&mut self, bounds: &[Rect2D]) {
+    (self.cells, self.oversized) = self.bucket(0, bounds);
+    self.bounds = bounds.to_vec();
+  }
+
+  fn build_on(&mut self, bounds: &[Rect2D], pool: &TaskPool) {
+    // Each batch fills its own grid; merging them in order keeps every
+    // cell's contents sorted, just as `build` leaves them
+    let grids = bounds.par_chunk_map(pool, BATCH_SIZE, |batch, rects| {
+      self.bucket(batch * BATCH_SIZE, rects)
+    });
+    self.cells.clear();
+    self.oversized.clear();
+    for (grid, oversized) in grids {
+      for (cell, contents) in grid {
+        self.cells.entry(cell).or_default().extend(contents);
+      }
+      self.oversized.extend(oversized);
+    }
+    self.bounds = bounds.to_vec();
+  }
+
+  fn query(&self, area: &Rect2D, found: &mut Vec<usize>) {
+    let start = found.len();
+    let (min, max) = self.cell_range(area);
+    let touches = |index: &&usize| self.bounds[**index].intersect(area);
+    if Self::cell_count(min, max) > self.cells.len() {
+      // A huge area: cheaper to look at every occupied cell
+      found.extend(self.cells.values().flatten().filter(touches));
+    } else {
+      for y in min.y..=max.y {
+        for x in min.x..=max.x {
+          if let Some(contents) = self.cells.get(&IVec2::new(x, y)) {
+            found.extend(contents.iter().filter(touches));
+          }
+        }
+      }
+    }
+    found.extend(self.oversized.iter().filter(touches));
+    // Colliders spanning several cells are seen once per cell
+    found[start..].sort_unstable();
+    dedup_from(found, start);
+  }
+
+  fn pairs(&self, found: &mut Vec<(usize, usize)>) {
+    let start = found.len();
+    for contents in self.cells.values() {
+      for (i, a) in contents.iter().enumerate() {
+        for b in &contents[i + 1..] {
+          if self.bounds[*a].intersect(&self.bounds[*b]) {
+            found.push((*a.min(b), *a.max(b)));
+          }
+        }
+      }
+    }
+    for a in &self.oversized {
+      found.extend(
+        (0..self.bounds.len())
+          .filter(|b| b != a && self.bounds[*a].intersect(&self.bounds[*b]))
+          .map(|b| (*a.min(&b), *a.max(&b))),
+      );
+    }
+    found[start..].sort_unstable();
+    dedup_from(found, start);
+  }
+}
+
+/// Sorts colliders by their left edge and sweeps across them, only
+/// comparing colliders whose horizontal extents overlap. Good for
+/// entities strung out along one axis, as in a side-scroller.
+#[derive(Clone, Debug, Default)]
+pub struct SweepAndPruneBroadphase {
+  bounds: Vec<Rect2D>,
+  order: Vec<usize>,
+}
+
+impl SweepAndPruneBroadphase {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Broadphase for SweepAndPruneBroadphase {
+  fn build(&mut self, bounds: &[Rect2D]) {
+    self.bounds = bounds.to_vec();
+    self.order = (0..bounds.len()).collect();
+    self.order.sort_unstable_by(|a, b| {
+      bounds[*a].min().x.total_cmp(&bounds[*b].min().x)
+    });
+  }
+
+  fn query(&self, area: &Rect2D, found: &mut Vec<usize>) {
+    found.extend(
+      self
+        .order
+        .iter()
+        .take_while(|index| self.bounds[**index].min().x <= area.max().x)
+        .filter(|index| self.bounds[**index].intersect(area)),
+    );
+  }
+
+  fn pairs(&self, found: &mut Vec<(usize, usize)>) {
+    let mut active: Vec<usize> = Vec::new();
+    for &index in &self.order {
+      let rect = &self.bounds[index];
+      active.retain(|other| self.bounds[*other].max().x >= rect.min().x);
+      found.extend(
+        active
+          .iter()
+          .filter(|other| self.bounds[**other].intersect(rect))
+          .map(|other| (index.min(*other), index.max(*other))),
+      );
+      active.push(index);
+    }
+  }
+}
+
+/// Files colliders in a [`DynamicQuadTree`], which only splits where
+/// colliders crowd together and grows to take in far-away ones. Good for
+/// scrolling worlds with uneven crowds; keep it on screen with
+/// [`follow_camera`].
+#[derive(Clone, Debug)]
+pub struct DynamicQuadTreeBroadphase {
+  tree: DynamicQuadTree<usize>,
+  bounds: Vec<Rect2D>,
+}
+
+impl DynamicQuadTreeBroadphase {
+  pub fn new(tree: DynamicQuadTree<usize>) -> Self {
+    Self {
+      tree,
+      bounds: Vec::new(),
+    }
+  }
+}
+
+impl Default for DynamicQuadTreeBroadphase {
+  /// Starts out covering a 1024x768 screen.
+  fn default() -> Self {
+    Self::new(DynamicQuadTree::new(Rect2D::new(
+      Vec2::new(-512.0, -384.0),
+      Vec2::new(512.0, 384.0),
+    )))
+  }
+}
+
+impl Broadphase for DynamicQuadTreeBroadphase {
+  fn build(&mut self, bounds: &[Rect2D]) {
+    self.bounds = bounds.to_vec();
+    self.tree.clear();
+    for (index, rect) in bounds.iter().enumerate() {
+      self.tree.insert(index, *rect);
+    }
+  }
+
+  fn query(&self, area: &Rect2D, found: &mut Vec<usize>) {
+    found.extend(self.tree.query(area).into_iter().map(|(index, _)| index));
+  }
+
+  fn pairs(&self, found: &mut Vec<(usize, usize)>) {
+    let start = found.len();
+    for (index, rect) in self.bounds.iter().enumerate() {
+      found.extend(
+        self
+          .tree
+          .query(rect)
+          .into_iter()
+          .filter(|(other, _)| *other != index)
+          .map(|(other, _)| (index.min(other), index.max(other))),
+      );
+    }
+    // Each pair is found from both ends
+    found[start..].sort_unstable();
+    dedup_from(found, start);
+  }
+
+  fn recenter(&mut self, center: Vec2) {
+    if self.tree.bounds().center() != center {
+      self.tree.recenter(center);
+    }
+  }
+}
+
+/// Keeps the broadphase used by the plugin marked `M` centred on the
+/// [`Camera2d`] whenever the camera moves. Nothing happens unless there
+/// is exactly one 2D camera.
+pub fn follow_camera<M: Send + Sync + 'static>(
+  camera: Query<&GlobalTransform, (With<Camera2d>, Changed<GlobalTransform>)>,
+  mut broadphase: ResMut<CollisionBroadphase<M>>,
+) {
+  if let Ok(camera) = camera.get_single() {
+    broadphase.backend_mut().recenter(camera.translation().truncate());
+  }
+}
+
+/// How many items each task takes when work is spread across a
+/// [`TaskPool`]. Smaller batches share the work out more evenly but
+/// cost more to schedule.
+const BATCH_SIZE: usize = 64;
+
+/// Maps `items` with `f` in batches across `pool`. The results keep the
+/// order of `items`, however many threads the pool has.
+pub(crate) fn par_map<T, R>(
+  pool: &TaskPool,
+  items: &[T],
+  f: impl Fn(&T) -> R + Send + Sync,
+) -> Vec<R>
+where
+  T: Sync,
+  R: Send + 'static,
+{
+  items
+    .par_chunk_map(pool, BATCH_SIZE, |_, batch| {
+      batch.iter().map(&f).collect::<Vec<R>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Looks up the colliders stored in `backend` near each of `items`,
+/// spreading `items` across `pool`. `test` is called with an item and
+/// the index of each collider touching `bounds(item)`, and whatever it
+/// returns is kept. Results are grouped by item in the order of
+/// `items`; within an item they follow the backend's order.
+pub fn par_query<T, R>(
+  backend: &dyn Broadphase,
+  pool: &TaskPool,
+  items: &[T],
+  bounds: impl Fn(&T) -> Rect2D + Send + Sync,
+  test: impl Fn(&T, usize) -> Option<R> + Send + Sync,
+) -> Vec<R>
+where
+  T: Sync,
+  R: Send + 'static,
+{
+  items
+    .par_chunk_map(pool, BATCH_SIZE, |_, batch| {
+      let mut found = Vec::new();
+      let mut nearby = Vec::new();
+      for item in batch {
+        nearby.clear();
+        backend.query(&bounds(item), &mut nearby);
+        found.extend(nearby.iter().filter_map(|index| test(item, *index)));
+      }
+      found
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Removes repeats from the sorted tail of `items` starting at `start`.
+fn dedup_from<T: PartialEq + Copy>(items: &mut Vec<T>, start: usize) {
+  let mut tail = items.split_off(start);
+  tail.dedup();
+  items.extend(tail);
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::bevy_framework::bevy_collision::rect2d::test::{
+    any_rect, scattered_rects,
+  };
+  use bevy::tasks::TaskPoolBuilder;
+  use proptest::prelude::*;
+
+  /// A few hundred boxes of mixed sizes, some off screen.
+  fn scattered() -> Vec<Rect2D> {
+    let area = Rect2D::new(Vec2::new(-700.0, -500.0), Vec2::new(700.0, 500.0));
+    scattered_rects(99, 400, area, 2.0..82.0)
+  }
+
+  fn brute_force_pairs(bounds: &[Rect2D]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for a in 0..bounds.len() {
+      for b in a + 1..bounds.len() {
+        if bounds[a].intersect(&bounds[b]) {
+          pairs.push((a, b));
+        }
+      }
+    }
+    pairs
+  }
+
+  fn backends() -> Vec<Box<dyn Broadphase>> {
+    vec![
+      Box::new(QuadTreeBroadphase::default()),
+      Box::new(SpatialHashBroadphase::new(64.0)),
+      Box::new(SweepAndPruneBroadphase::new()),
+      Box::new(DynamicQuadTreeBroadphase::default()),
+    ]
+  }
+
+  #[test]
+  fn test_backends_find_the_same_pairs() {
+    let bounds = scattered();
+    let expected = brute_force_pairs(&bounds);
+    assert!(!expected.is_empty());
+    for mut backend in backends() {
+      backend.build(&bounds);
+      let mut pairs = Vec::new();
+      backend.pairs(&mut pairs);
+      pairs.sort_unstable();
+      assert_eq!(pairs, expected);
+    }
+  }
+
+  #[test]
+  fn test_backends_answer_queries_alike() {
+    let bounds = scattered();
+    let areas = [
+      Rect2D::new(Vec2::new(-100.0, -100.0), Vec2::new(100.0, 50.0)),
+      Rect2D::new(Vec2::new(600.0, 400.0), Vec2::new(800.0, 600.0)),
+      Rect2D::new(Vec2::splat(-5000.0), Vec2::splat(5000.0)),
+      Rect2D::new(Vec2::ZERO, Vec2::ZERO),
+    ];
+    for mut backend in backends() {
+      backend.build(&bounds);
+      for area in &areas {
+        let mut found = Vec::new();
+        backend.query(area, &mut found);
+        found.sort_unstable();
+        let expected: Vec<usize> = (0..bounds.len())
+          .filter(|index| bounds[*index].intersect(area))
+          .collect();
+        assert_eq!(found, expected);
+      }
+    }
+  }
+
+  #[test]
+  fn test_par_query_keeps_order_on_any_pool() {
+    let bounds = scattered();
+    let mut backend = QuadTreeBroadphase::default();
+    backend.build(&bounds);
+    let items: Vec<usize> = (0..bounds.len()).collect();
+    let mut expected = Vec::new();
+    for item in &items {
+      let mut nearby = Vec::new();
+      backend.query(&bounds[*item], &mut nearby);
+      expected.extend(
+        nearby
+          .iter()
+          .filter(|index| *index > item)
+          .map(|index| (*item, *index)),
+      );
+    }
+    assert!(!expected.is_empty());
+    for threads in [1, 4] {
+      let pool = TaskPoolBuilder::new().num_threads(threads).build();
+      let found = par_query(
+        &backend,
+        &pool,
+        &items,
+        |item| bounds[*item],
+        |item, index| (index > *item).then_some((*item, index)),
+      );
+      assert_eq!(found, expected);
+    }
+  }
+
+  #[test]
+  fn test_build_on_any_pool_matches_build() {
+    let bounds = scattered();
+    let area =
+      Rect2D::new(Vec2::new(-300.0, -200.0), Vec2::new(250.0, 100.0));
+    for threads in [1, 4] {
+      let pool = TaskPoolBuilder::new().num_threads(threads).build();
+      for (mut serial, mut parallel) in backends().into_iter().zip(backends())
+      {
+        serial.build(&bounds);
+        parallel.build_on(&bounds, &pool);
+        let (mut expected, mut found) = (Vec::new(), Vec::new());
+        serial.pairs(&mut expected);
+        parallel.pairs(&mut found);
+        assert_eq!(found, expected);
+        let (mut expected, mut found) = (Vec::new(), Vec::new());
+        serial.query(&area, &mut expected);
+        parallel.query(&area, &mut found);
+        expected.sort_unstable();
+        found.sort_unstable();
+        assert_eq!(found, expected);
+      }
+    }
+  }
+
+  #[test]
+  fn test_spatial_hash_keeps_huge_colliders_aside() {
+    let mut bounds = scattered();
+    let wide = Rect2D::new(Vec2::new(-5e5, 0.0), Vec2::new(5e5, 16.0));
+    let endless = Rect2D::new(Vec2::splat(-1e30), Vec2::splat(1e30));
+    let far = Rect2D::new(Vec2::splat(1e30), Vec2::splat(1e30 + 1e28));
+    bounds.extend([wide, endless, far]);
+    let expected = brute_force_pairs(&bounds);
+    let area = Rect2D::new(Vec2::new(-300.0, -8.0), Vec2::new(300.0, 8.0));
+    let expected_found: Vec<usize> = (0..bounds.len())
+      .filter(|index| bounds[*index].intersect(&area))
+      .collect();
+
+    let pool = TaskPoolBuilder::new().num_threads(4).build();
+    let mut serial = SpatialHashBroadphase::new(16.0);
+    let mut parallel = SpatialHashBroadphase::new(16.0);
+    serial.build(&bounds);
+    parallel.build_on(&bounds, &pool);
+    // The far one saturates into a single cell at the edge of the grid
+    assert_eq!(serial.oversized, vec![bounds.len() - 3, bounds.len() - 2]);
+    for backend in [serial, parallel] {
+      let mut pairs = Vec::new();
+      backend.pairs(&mut pairs);
+      pairs.sort_unstable();
+      assert_eq!(pairs, expected);
+      let mut found = Vec::new();
+      backend.query(&area, &mut found);
+      assert_eq!(found, expected_found);
+    }
+  }
+
+  #[test]
+  fn test_rebuild_replaces_contents() {
+    for mut backend in backends() {
+      let square = Rect2D::new(Vec2::ZERO, Vec2::splat(10.0));
+      backend.build(&[square, square]);
+      backend.build(&[square]);
+      let mut pairs = Vec::new();
+      backend.pairs(&mut pairs);
+      assert!(pairs.is_empty());
+    }
+  }
+
+  /// Remembers every centre it is moved to.
+  struct Recenters(std::sync::Arc<std::sync::Mutex<Vec<Vec2>>>);
+
+  impl Broadphase for Recenters {
+    fn build(&mut self, _bounds: &[Rect2D]) {}
+    fn query(&self, _area: &Rect2D, _found: &mut Vec<usize>) {}
+    fn pairs(&self, _found: &mut Vec<(usize, usize)>) {}
+    fn recenter(&mut self, center: Vec2) {
+      self.0.lock().unwrap().push(center);
+    }
+  }
+
+  #[test]
+  fn test_follow_camera_only_when_it_moves() {
+    let centers = std::sync::Arc::default();
+    let mut world = World::new();
+    world.insert_resource(CollisionBroadphase::<()>::new(Recenters(
+      std::sync::Arc::clone(&centers),
+    )));
+    // A registered system remembers which changes it has seen
+    let follow = world.register_system(follow_camera::<()>);
+    let camera = world
+      .spawn((Camera2d, GlobalTransform::from_xyz(3000.0, 200.0, 0.0)))
+      .id();
+    world.run_system(follow).unwrap();
+    world.run_system(follow).unwrap();
+    world
+      .entity_mut(camera)
+      .insert(GlobalTransform::from_xyz(3100.0, 200.0, 0.0));
+    world.run_system(follow).unwrap();
+    assert_eq!(
+      *centers.lock().unwrap(),
+      vec![Vec2::new(3000.0, 200.0), Vec2::new(3100.0, 200.0)]
+    );
+  }
+
+  #[test]
+  fn test_dynamic_tree_recenters_without_growing() {
+    let mut backend = DynamicQuadTreeBroadphase::default();
+    backend.recenter(Vec2::new(3000.0, 200.0));
+    assert_eq!(backend.tree.bounds().center(), Vec2::new(3000.0, 200.0));
+    assert_eq!(backend.tree.bounds().size(), Vec2::new(1024.0, 768.0));
+
+    // Colliders on the new screen split it rather than stretch it
+    let crowd: Vec<Rect2D> = (0..40)
+      .map(|i| {
+        let corner = Vec2::new(2600.0 + i as f32 * 15.0, 0.0);
+        Rect2D::new(corner, corner + Vec2::splat(10.0))
+      })
+      .collect();
+    backend.build(&crowd);
+    assert_eq!(backend.tree.bounds().size(), Vec2::new(1024.0, 768.0));
+    assert!(backend.tree.node_count() > 1);
+
+    // One stray grows the tree for a frame, not for good
+    let mut strayed = crowd.clone();
+    strayed.push(Rect2D::new(Vec2::splat(90_000.0), Vec2::splat(90_010.0)));
+    backend.build(&strayed);
+    assert!(backend.tree.bounds().size().x > 90_000.0);
+    backend.build(&crowd);
+    assert_eq!(backend.tree.bounds().size(), Vec2::new(1024.0, 768.0));
+    assert_eq!(backend.tree.bounds().center(), Vec2::new(3000.0, 200.0));
+  }
+
+  proptest! {
+    #[test]
+    fn quadtree_pairs_match_brute_force(
+      width in 100.0_f32..3000.0,
+      height in 100.0_f32..3000.0,
+      depth in 1_usize..5,
+      bounds in prop::collection::vec(any_rect(), 0..60),
+    ) {
+      let tree = StaticQuadTree::new(Vec2::new(width, height), depth);
+      let mut backend = QuadTreeBroadphase::new(tree);
+      backend.build(&bounds);
+      let mut pairs = Vec::new();
+      backend.pairs(&mut pairs);
+      pairs.sort_unstable();
+      prop_assert_eq!(pairs, brute_force_pairs(&bounds));
+    }
+
+    #[test]
+    fn quadtree_queries_match_brute_force(
+      width in 100.0_f32..3000.0,
+      height in 100.0_f32..3000.0,
+      bounds in prop::collection::vec(any_rect(), 0..60),
+      area in any_rect(),
+    ) {
+      let tree = StaticQuadTree::new(Vec2::new(width, height), 4);
+      let mut backend = QuadTreeBroadphase::new(tree);
+      backend.build(&bounds);
+      let mut found = Vec::new();
+      backend.query(&area, &mut found);
+      found.sort_unstable();
+      let expected: Vec<usize> = (0..bounds.len())
+        .filter(|index| bounds[*index].intersect(&area))
+        .collect();
+      prop_assert_eq!(found, expected);
+    }
+  }
+}
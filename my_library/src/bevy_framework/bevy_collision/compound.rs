@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use super::{
+  rect2d::Rect2D, AxisAlignedBoundingBox, CapsuleCollider, CircleCollider,
+  ConvexPolygonCollider, OrientedBox, Shape,
+};
+
+/// Moves an entity's collider away from its origin. The offset is in
+/// the entity's own space, so it turns and scales with the entity.
+/// Good for trimming a box down to the solid part of a sprite.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ColliderOffset(pub Vec2);
+
+/// Any one of the collider components, as a part of a
+/// [`CompoundCollider`].
+#[derive(Clone, Debug)]
+pub enum ColliderShape {
+  Aabb(AxisAlignedBoundingBox),
+  Circle(CircleCollider),
+  Capsule(CapsuleCollider),
+  Oriented(OrientedBox),
+  Polygon(ConvexPolygonCollider),
+}
+
+impl From<AxisAlignedBoundingBox> for ColliderShape {
+  fn from(aabb: AxisAlignedBoundingBox) -> Self {
+    Self::Aabb(aabb)
+  }
+}
+
+impl From<CircleCollider> for ColliderShape {
+  fn from(circle: CircleCollider) -> Self {
+    Self::Circle(circle)
+  }
+}
+
+impl From<CapsuleCollider> for ColliderShape {
+  fn from(capsule: CapsuleCollider) -> Self {
+    Self::Capsule(capsule)
+  }
+}
+
+impl From<OrientedBox> for ColliderShape {
+  fn from(oriented: OrientedBox) -> Self {
+    Self::Oriented(oriented)
+  }
+}
+
+impl From<ConvexPolygonCollider> for ColliderShape {
+  fn from(polygon: ConvexPolygonCollider) -> Self {
+    Self::Polygon(polygon)
+  }
+}
+
+impl ColliderShape {
+  /// Places the shape `offset` from the origin of an entity at
+  /// `transform`. Every shape is scaled by the transform, but only
+  /// oriented boxes and polygons turn with it; boxes stay upright and
+  /// capsules vertical. Circles grow with the larger of the x and y
+  /// scales.
+  pub fn place(&self, transform: &Transform, offset: Vec2) -> Shape {
+    let center = transform.transform_point(offset.extend(0.0)).truncate();
+    let scale = transform.scale.truncate().abs();
+    match self {
+      ColliderShape::Aabb(aabb) => {
+        let half = aabb.size() * scale / 2.0;
+        Shape::Rect(Rect2D::new(center - half, center + half))
+      }
+      ColliderShape::Circle(circle) => Shape::Circle {
+        center,
+        radius: circle.radius() * scale.max_element(),
+      },
+      ColliderShape::Capsule(capsule) => {
+        let half = Vec2::new(0.0, capsule.half_length() * scale.y);
+        Shape::Capsule {
+          a: center - half,
+          b: center + half,
+          radius: capsule.radius() * scale.x,
+        }
+      }
+      ColliderShape::Oriented(oriented) => {
+        Shape::polygon(transform, &offset_points(&oriented.corners(), offset))
+      }
+      ColliderShape::Polygon(polygon) => {
+        Shape::polygon(transform, &offset_points(polygon.points(), offset))
+      }
+    }
+  }
+}
+
+fn offset_points(points: &[Vec2], offset: Vec2) -> Vec<Vec2> {
+  points.iter().map(|point| *point + offset).collect()
+}
+
+/// Several shapes making up one entity's collider, each at its own
+/// offset from the entity's origin. The entity collides wherever any
+/// of its parts does.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use my_library::*;
+/// let dragon = CompoundCollider::new()
+///   .with(Vec2::new(8.0, 4.0), CircleCollider::new(18.0))
+///   .with(Vec2::new(-16.0, -6.0), OrientedBox::new(20.0, 12.0));
+/// assert_eq!(dragon.parts().len(), 2);
+/// ```
+#[derive(Component, Clone, Debug, Default)]
+pub struct CompoundCollider {
+  parts: Vec<(Vec2, ColliderShape)>,
+}
+
+impl CompoundCollider {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with(mut self, offset: Vec2, shape: impl Into<ColliderShape>) -> Self {
+    self.parts.push((offset, shape.into()));
+    self
+  }
+
+  pub fn parts(&self) -> &[(Vec2, ColliderShape)] {
+    &self.parts
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_offset_turns_with_entity() {
+    let transform = Transform::from_xyz(100.0, 0.0, 0.0)
+      .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+    let shape = ColliderShape::from(CircleCollider::new(5.0))
+      .place(&transform, Vec2::new(10.0, 0.0));
+    let Shape::Circle { center, radius } = shape else {
+      panic!("Expected a circle");
+    };
+    assert!(center.distance(Vec2::new(100.0, 10.0)) < 0.001);
+    assert_eq!(radius, 5.0);
+  }
+
+  #[test]
+  fn test_scale_grows_shapes_and_offsets() {
+    let transform = Transform::from_scale(Vec3::new(2.0, 3.0, 1.0));
+    let aabb = ColliderShape::from(AxisAlignedBoundingBox::new(10.0, 10.0))
+      .place(&transform, Vec2::new(5.0, 0.0));
+    assert_eq!(
+      aabb,
+      Shape::Rect(Rect2D::new(Vec2::new(0.0, -15.0), Vec2::new(20.0, 15.0)))
+    );
+    let circle = ColliderShape::from(CircleCollider::new(4.0))
+      .place(&transform, Vec2::ZERO);
+    assert_eq!(circle.bounds().max(), Vec2::splat(12.0));
+  }
+
+  #[test]
+  fn test_negative_scale_keeps_size() {
+    let transform = Transform::from_scale(Vec3::new(-1.0, 1.0, 1.0));
+    let aabb = ColliderShape::from(AxisAlignedBoundingBox::new(10.0, 4.0))
+      .place(&transform, Vec2::new(3.0, 0.0));
+    assert_eq!(
+      aabb,
+      Shape::Rect(Rect2D::new(Vec2::new(-8.0, -2.0), Vec2::new(2.0, 2.0)))
+    );
+  }
+}
@@ -0,0 +1,490 @@
+use bevy::{prelude::*, utils::HashMap};
+use super::rect2d::Rect2D;
+use std::hash::Hash;
+
+/// A quadtree that stores entities directly. Unlike [`StaticQuadTree`]
+/// it starts as a single node and only splits a node once it holds more
+/// than `node_capacity` entities, so empty space costs nothing. Its
+/// bounds can grow to take in far-away entities and shrink again once
+/// they're gone, or follow a scrolling camera with
+/// [`recenter`](Self::recenter).
+///
+/// Entries are keyed by [`Entity`] unless another key is given; the
+/// [`DynamicQuadTreeBroadphase`] keys them by collider index.
+///
+/// [`StaticQuadTree`]: super::StaticQuadTree
+/// [`DynamicQuadTreeBroadphase`]: super::DynamicQuadTreeBroadphase
+#[derive(Debug, Clone, Resource)]
+pub struct DynamicQuadTree<K = Entity> {
+  nodes: Vec<DynamicQuadTreeNode<K>>,
+  locations: HashMap<K, usize>,
+  node_capacity: usize,
+  max_depth: usize,
+  growable: bool,
+  /// The bounds before any growth. Emptying, recentring or shrinking
+  /// the tree goes back to this size.
+  home: Rect2D,
+}
+
+#[derive(Debug, Clone)]
+struct DynamicQuadTreeNode<K> {
+  bounds: Rect2D,
+  depth: usize,
+  children: Option<[usize; 4]>,
+  entries: Vec<(K, Rect2D)>,
+}
+
+impl<K> DynamicQuadTreeNode<K> {
+  fn new(bounds: Rect2D, depth: usize) -> Self {
+    Self {
+      bounds,
+      depth,
+      children: None,
+      entries: Vec::new(),
+    }
+  }
+}
+
+impl<K: Copy + Eq + Hash> DynamicQuadTree<K> {
+  pub fn new(bounds: Rect2D) -> Self {
+    Self {
+      nodes: vec![DynamicQuadTreeNode::new(bounds, 0)],
+      locations: HashMap::new(),
+      node_capacity: 8,
+      max_depth: 8,
+      growable: true,
+      home: bounds,
+    }
+  }
+
+  /// How many entities a node holds before it splits. Defaults to 8.
+  pub fn with_node_capacity(mut self, node_capacity: usize) -> Self {
+    self.node_capacity = node_capacity.max(1);
+    self
+  }
+
+  /// How many times the bounds may be split. Defaults to 8.
+  pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+    self.max_depth = max_depth;
+    self
+  }
+
+  /// Whether inserting an entity outside the bounds grows them. If not,
+  /// such entities are kept in the root node. Defaults to `true`.
+  pub fn with_growth(mut self, growable: bool) -> Self {
+    self.growable = growable;
+    self
+  }
+
+  pub fn bounds(&self) -> Rect2D {
+    self.nodes[0].bounds
+  }
+
+  pub fn len(&self) -> usize {
+    self.locations.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.locations.is_empty()
+  }
+
+  /// How many nodes the tree has split into.
+  pub fn node_count(&self) -> usize {
+    self.nodes.len()
+  }
+
+  /// Removes every entity, returning the bounds to their starting size.
+  pub fn clear(&mut self) {
+    self.nodes = vec![DynamicQuadTreeNode::new(self.home, 0)];
+    self.locations.clear();
+  }
+
+  /// Adds `entity` covering `rect`, or moves it there if it is already
+  /// in the tree.
+  pub fn insert(&mut self, entity: K, rect: Rect2D) {
+    self.remove(entity);
+    if self.growable && !contains_rect(&self.bounds(), &rect) {
+      self.grow_to_include(&rect);
+    }
+    self.place(entity, rect);
+  }
+
+  /// Takes `entity` out of the tree, returning whether it was there.
+  pub fn remove(&mut self, entity: K) -> bool {
+    let Some(node) = self.locations.remove(&entity) else {
+      return false;
+    };
+    let entries = &mut self.nodes[node].entries;
+    if let Some(index) = entries.iter().position(|(e, _)| *e == entity) {
+      entries.swap_remove(index);
+    }
+    true
+  }
+
+  /// Where `entity` was last inserted.
+  pub fn get(&self, entity: K) -> Option<Rect2D> {
+    let node = self.locations.get(&entity)?;
+    self.nodes[*node]
+      .entries
+      .iter()
+      .find(|(e, _)| *e == entity)
+      .map(|(_, rect)| *rect)
+  }
+
+  /// Every entity whose rectangle touches `area`.
+  pub fn query(&self, area: &Rect2D) -> Vec<(K, Rect2D)> {
+    let mut result = Vec::new();
+    let mut stack = vec![0];
+    while let Some(index) = stack.pop() {
+      let node = &self.nodes[index];
+      // The root is always searched: it holds anything outside the bounds
+      if index != 0 && !node.bounds.intersect(area) {
+        continue;
+      }
+      result.extend(
+        node.entries.iter().filter(|(_, rect)| rect.intersect(area)),
+      );
+      if let Some(children) = node.children {
+        stack.extend(children);
+      }
+    }
+    result
+  }
+
+  /// Grows the bounds until they contain `rect` and everything already
+  /// in the tree. Growth starts again from the starting size, so room
+  /// made for entities that have since left is given back.
+  pub fn grow_to_include(&mut self, rect: &Rect2D) {
+    if !is_finite(rect) {
+      return;
+    }
+    let bounds = self.fitted(grown(self.home, rect));
+    self.rebuild(bounds);
+  }
+
+  /// Shrinks the bounds as close to their starting size as everything
+  /// in the tree allows. Worth calling after far-away entities leave.
+  pub fn shrink_to_fit(&mut self) {
+    let bounds = self.fitted(self.home);
+    if bounds != self.bounds() {
+      self.rebuild(bounds);
+    }
+  }
+
+  /// Moves the bounds so they are centred on `center`, back at their
+  /// starting size. Call it as the camera scrolls; entities left outside
+  /// stay in the root node until they are next inserted.
+  pub fn recenter(&mut self, center: Vec2) {
+    let half = self.home.size() / 2.0;
+    self.home = Rect2D::new(center - half, center + half);
+    self.rebuild(self.home);
+  }
+
+  /// `start`, grown to take in every entity in the tree.
+  fn fitted(&self, start: Rect2D) -> Rect2D {
+    self
+      .nodes
+      .iter()
+      .flat_map(|node| &node.entries)
+      .fold(start, |bounds, (_, rect)| grown(bounds, rect))
+  }
+
+  fn rebuild(&mut self, bounds: Rect2D) {
+    let entries: Vec<(K, Rect2D)> = self
+      .nodes
+      .iter_mut()
+      .flat_map(|node| node.entries.drain(..))
+      .collect();
+    self.nodes = vec![DynamicQuadTreeNode::new(bounds, 0)];
+    self.locations.clear();
+    for (entity, rect) in entries {
+      self.place(entity, rect);
+    }
+  }
+
+  /// Stores the entity in the deepest node that wholly contains it,
+  /// splitting that node if it's now too full.
+  fn place(&mut self, entity: K, rect: Rect2D) {
+    let index = self.deepest_node(0, &rect);
+    self.nodes[index].entries.push((entity, rect));
+    self.locations.insert(entity, index);
+
+    let node = &self.nodes[index];
+    if node.children.is_none()
+      && node.entries.len() > self.node_capacity
+      && node.depth < self.max_depth
+    {
+      self.split(index);
+    }
+  }
+
+  fn deepest_node(&self, mut index: usize, rect: &Rect2D) -> usize {
+    while let Some(children) = self.nodes[index].children {
+      match children
+        .iter()
+        .find(|child| contains_rect(&self.nodes[**child].bounds, rect))
+      {
+        Some(child) => index = *child,
+        None => break,
+      }
+    }
+    index
+  }
+
+  fn split(&mut self, index: usize) {
+    let depth = self.nodes[index].depth + 1;
+    let first = self.nodes.len();
+    for quadrant in self.nodes[index].bounds.quadrants() {
+      self.nodes.push(DynamicQuadTreeNode::new(quadrant, depth));
+    }
+    self.nodes[index].children =
+      Some([first, first + 1, first + 2, first + 3]);
+
+    // Push entries down into the children that can hold them
+    let entries = std::mem::take(&mut self.nodes[index].entries);
+    for (entity, rect) in entries {
+      let target = self.deepest_node(index, &rect);
+      self.nodes[target].entries.push((entity, rect));
+      self.locations.insert(entity, target);
+    }
+    for child in first..first + 4 {
+      let node = &self.nodes[child];
+      if node.entries.len() > self.node_capacity && depth < self.max_depth {
+        self.split(child);
+      }
+    }
+  }
+}
+
+/// Doubles `bounds`, away from their centre towards `rect`, until they
+/// contain it. Bounds with no area are first stretched to cover `rect`.
+/// A `rect` that isn't finite can never be reached, so it leaves the
+/// bounds alone and ends up in the root node.
+fn grown(mut bounds: Rect2D, rect: &Rect2D) -> Rect2D {
+  if !is_finite(rect) {
+    return bounds;
+  }
+  let size = bounds.size();
+  if !(bounds.min().is_finite() && size.is_finite())
+    || size.cmple(Vec2::ZERO).any()
+  {
+    // Doubling nothing stays nothing
+    let start = if is_finite(&bounds) {
+      bounds.union(rect)
+    } else {
+      *rect
+    };
+    bounds = Rect2D::new(
+      start.min(),
+      start.max().max(start.min() + Vec2::ONE),
+    );
+  }
+  while !contains_rect(&bounds, rect) {
+    let size = bounds.size();
+    let towards = rect.center() - bounds.center();
+    let shift = Vec2::new(
+      if towards.x < 0.0 { size.x } else { 0.0 },
+      if towards.y < 0.0 { size.y } else { 0.0 },
+    );
+    let min = bounds.min() - shift;
+    bounds = Rect2D::new(min, min + size * 2.0);
+  }
+  bounds
+}
+
+fn is_finite(rect: &Rect2D) -> bool {
+  rect.min().is_finite() && rect.max().is_finite()
+}
+
+fn contains_rect(outer: &Rect2D, inner: &Rect2D) -> bool {
+  outer.contains(inner.min()) && outer.contains(inner.max())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::bevy_framework::bevy_collision::rect2d::test::{
+    any_rect, random_points, scattered_rects,
+  };
+  use proptest::prelude::*;
+
+  fn square(x: f32, y: f32, size: f32) -> Rect2D {
+    let half = Vec2::splat(size / 2.0);
+    Rect2D::new(Vec2::new(x, y) - half, Vec2::new(x, y) + half)
+  }
+
+  fn screen() -> Rect2D {
+    Rect2D::new(Vec2::new(-512.0, -384.0), Vec2::new(512.0, 384.0))
+  }
+
+  fn sorted(found: Vec<(Entity, Rect2D)>) -> Vec<Entity> {
+    let mut entities: Vec<Entity> =
+      found.into_iter().map(|(entity, _)| entity).collect();
+    entities.sort();
+    entities
+  }
+
+  #[test]
+  fn test_splits_only_where_dense() {
+    let mut tree = DynamicQuadTree::new(screen()).with_node_capacity(4);
+    for i in 0..4 {
+      tree.insert(Entity::from_raw(i), square(-400.0, 0.0, 4.0));
+    }
+    assert_eq!(tree.node_count(), 1);
+
+    // A crowd in one corner splits the tree there and nowhere else
+    for i in 4..40 {
+      let offset = i as f32;
+      tree.insert(Entity::from_raw(i), square(300.0 + offset, 300.0, 2.0));
+    }
+    assert!(tree.node_count() > 5);
+    let far_corner = Rect2D::new(Vec2::new(-512.0, -384.0), Vec2::ZERO);
+    assert_eq!(tree.nodes[tree.deepest_node(0, &far_corner)].depth, 1);
+  }
+
+  #[test]
+  fn test_query_matches_brute_force() {
+    let mut tree = DynamicQuadTree::new(screen()).with_node_capacity(2);
+    let mut all = Vec::new();
+    for (i, rect) in scattered_rects(1, 300, screen(), 0.0..30.0)
+      .into_iter()
+      .enumerate()
+    {
+      tree.insert(Entity::from_raw(i as u32), rect);
+      all.push((Entity::from_raw(i as u32), rect));
+    }
+    for point in random_points(2, 50, screen()) {
+      let area = square(point.x, point.y, 100.0);
+      let expected: Vec<(Entity, Rect2D)> = all
+        .iter()
+        .filter(|(_, rect)| rect.intersect(&area))
+        .copied()
+        .collect();
+      assert_eq!(sorted(tree.query(&area)), sorted(expected));
+    }
+  }
+
+  #[test]
+  fn test_grows_to_include_far_entities() {
+    let mut tree = DynamicQuadTree::new(screen());
+    let far = Entity::from_raw(1);
+    tree.insert(far, square(5000.0, 0.0, 10.0));
+    assert!(contains_rect(&tree.bounds(), &square(5000.0, 0.0, 10.0)));
+    assert_eq!(sorted(tree.query(&square(5000.0, 0.0, 1.0))), vec![far]);
+
+    let mut fixed = DynamicQuadTree::new(screen()).with_growth(false);
+    fixed.insert(far, square(5000.0, 0.0, 10.0));
+    assert_eq!(fixed.bounds(), screen());
+    assert_eq!(sorted(fixed.query(&square(5000.0, 0.0, 1.0))), vec![far]);
+  }
+
+  #[test]
+  fn test_shrinks_back_once_far_entities_leave() {
+    let mut tree = DynamicQuadTree::new(screen());
+    let near = Entity::from_raw(1);
+    let far = Entity::from_raw(2);
+    tree.insert(near, square(0.0, 0.0, 10.0));
+    tree.insert(far, square(50_000.0, 0.0, 10.0));
+    let grown = tree.bounds();
+    assert!(grown.size().x > 50_000.0);
+
+    // Growing elsewhere forgets the room made for the stray that left
+    tree.remove(far);
+    tree.insert(far, square(-3000.0, 0.0, 10.0));
+    assert!(tree.bounds().size().x < grown.size().x);
+    assert!(contains_rect(&tree.bounds(), &square(-3000.0, 0.0, 10.0)));
+
+    tree.remove(far);
+    tree.shrink_to_fit();
+    assert_eq!(tree.bounds(), screen());
+    assert_eq!(sorted(tree.query(&square(0.0, 0.0, 1.0))), vec![near]);
+
+    tree.insert(far, square(50_000.0, 0.0, 10.0));
+    tree.clear();
+    assert_eq!(tree.bounds(), screen());
+
+    tree.insert(far, square(50_000.0, 0.0, 10.0));
+    tree.recenter(Vec2::new(100.0, 0.0));
+    assert_eq!(tree.bounds().size(), screen().size());
+    assert_eq!(tree.len(), 1);
+  }
+
+  #[test]
+  fn test_empty_bounds_still_grow() {
+    let point = Rect2D::new(Vec2::ZERO, Vec2::ZERO);
+    let mut tree = DynamicQuadTree::new(point);
+    let far = Entity::from_raw(1);
+    tree.insert(far, square(300.0, -40.0, 10.0));
+    assert!(contains_rect(&tree.bounds(), &square(300.0, -40.0, 10.0)));
+    assert_eq!(sorted(tree.query(&square(300.0, -40.0, 1.0))), vec![far]);
+
+    // A flat line grows too, not just a point
+    let line = Rect2D::new(Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0));
+    let mut tree = DynamicQuadTree::new(line);
+    tree.insert(far, square(0.0, 500.0, 10.0));
+    assert!(contains_rect(&tree.bounds(), &square(0.0, 500.0, 10.0)));
+  }
+
+  #[test]
+  fn test_non_finite_rects_stay_in_the_root() {
+    let mut tree = DynamicQuadTree::new(screen());
+    let lost = Entity::from_raw(1);
+    let nan = Rect2D::new(Vec2::NAN, Vec2::NAN);
+    tree.insert(lost, nan);
+    tree.insert(Entity::from_raw(2), square(f32::INFINITY, 0.0, 10.0));
+    assert_eq!(tree.bounds(), screen());
+    assert_eq!(tree.len(), 2);
+
+    let mut broken = DynamicQuadTree::new(nan);
+    broken.insert(lost, square(100.0, 100.0, 10.0));
+    assert!(contains_rect(&broken.bounds(), &square(100.0, 100.0, 10.0)));
+  }
+
+  #[test]
+  fn test_recenter_keeps_entities() {
+    let mut tree = DynamicQuadTree::new(screen()).with_node_capacity(1);
+    for i in 0..10 {
+      tree.insert(Entity::from_raw(i), square(i as f32 * 50.0, 0.0, 4.0));
+    }
+    tree.recenter(Vec2::new(2000.0, 0.0));
+    assert_eq!(tree.bounds().center(), Vec2::new(2000.0, 0.0));
+    assert_eq!(tree.len(), 10);
+    let everything = Rect2D::new(Vec2::splat(-1000.0), Vec2::splat(1000.0));
+    assert_eq!(tree.query(&everything).len(), 10);
+  }
+
+  #[test]
+  fn test_update_and_remove() {
+    let mut tree = DynamicQuadTree::new(screen()).with_node_capacity(1);
+    let a = Entity::from_raw(1);
+    let b = Entity::from_raw(2);
+    tree.insert(a, square(-200.0, 0.0, 4.0));
+    tree.insert(b, square(200.0, 0.0, 4.0));
+    tree.insert(a, square(200.0, 10.0, 4.0));
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree.get(a), Some(square(200.0, 10.0, 4.0)));
+    assert!(tree.query(&square(-200.0, 0.0, 10.0)).is_empty());
+
+    assert!(tree.remove(b));
+    assert!(!tree.remove(b));
+    assert_eq!(sorted(tree.query(&square(200.0, 5.0, 20.0))), vec![a]);
+  }
+
+  proptest! {
+    #[test]
+    fn queries_match_brute_force(
+      rects in prop::collection::vec(any_rect(), 0..80),
+      area in any_rect(),
+    ) {
+      let mut tree = DynamicQuadTree::new(screen()).with_node_capacity(2);
+      for (i, rect) in rects.iter().enumerate() {
+        tree.insert(Entity::from_raw(i as u32), *rect);
+      }
+      let expected: Vec<Entity> = (0..rects.len())
+        .filter(|i| rects[*i].intersect(&area))
+        .map(|i| Entity::from_raw(i as u32))
+        .collect();
+      prop_assert_eq!(sorted(tree.query(&area)), expected);
+    }
+  }
+}
@@ -0,0 +1,218 @@
+use bevy::prelude::*;
+use super::{
+  rect2d::Rect2D, ColliderQuery, CollisionBroadphase, ContactManifold, Swept,
+  WithCollider,
+};
+
+/// Which layers an entity is on (`memberships`) and which layers it
+/// collides with (`filters`), as bitmasks. Two entities collide only if
+/// each is on a layer the other's filter accepts.
+///
+/// ```
+/// # use my_library::CollisionLayers;
+/// const PLAYER: u32 = 1 << 0;
+/// const ENEMY: u32 = 1 << 1;
+/// const PICKUP: u32 = 1 << 2;
+///
+/// let player = CollisionLayers::new(PLAYER, ENEMY | PICKUP);
+/// let enemy = CollisionLayers::new(ENEMY, PLAYER);
+/// let pickup = CollisionLayers::new(PICKUP, PLAYER);
+/// assert!(player.interacts_with(&enemy));
+/// assert!(!enemy.interacts_with(&pickup));
+/// ```
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionLayers {
+  pub memberships: u32,
+  pub filters: u32,
+}
+
+impl Default for CollisionLayers {
+  /// On the first layer, colliding with everything.
+  fn default() -> Self {
+    Self::new(1, Self::ALL)
+  }
+}
+
+impl CollisionLayers {
+  pub const ALL: u32 = u32::MAX;
+  pub const NONE: u32 = 0;
+
+  pub fn new(memberships: u32, filters: u32) -> Self {
+    Self {
+      memberships,
+      filters,
+    }
+  }
+
+  pub fn interacts_with(&self, other: &Self) -> bool {
+    self.memberships & other.filters != 0
+      && other.memberships & self.filters != 0
+  }
+}
+
+/// Sent by [`check_layer_collisions`] once per frame for every pair of
+/// touching entities whose [`CollisionLayers`] interact. Each pair is
+/// reported once, in no particular order.
+#[derive(Event, Debug, Clone)]
+pub struct LayerCollision {
+  pub entity_a: Entity,
+  pub entity_b: Entity,
+  pub layers_a: CollisionLayers,
+  pub layers_b: CollisionLayers,
+  /// When either entity has
+  /// [`ContinuousCollision`](super::ContinuousCollision), how far
+  /// through this frame's movement (0 to 1) the two first touched.
+  pub time_of_impact: Option<f32>,
+  /// Where and how deeply the two overlap at the end of the frame. The
+  /// normal points from `entity_b` towards `entity_a`.
+  pub manifold: Option<ContactManifold>,
+}
+
+/// Tests every entity with [`CollisionLayers`] and a collider against
+/// every other in a single broadphase pass.
+pub fn check_layer_collisions(
+  mut broadphase: ResMut<CollisionBroadphase<LayerCollision>>,
+  query: Query<(ColliderQuery, &CollisionLayers), WithCollider>,
+  mut sender: EventWriter<LayerCollision>,
+) {
+  let colliders: Vec<(Entity, CollisionLayers, Swept)> = query
+    .iter()
+    .map(|(collider, layers)| (collider.entity, *layers, Swept::new(&collider)))
+    .collect();
+  let bounds: Vec<Rect2D> =
+    colliders.iter().map(|(_, _, swept)| swept.bounds()).collect();
+  broadphase.backend_mut().build(&bounds);
+
+  let mut pairs = Vec::new();
+  broadphase.backend().pairs(&mut pairs);
+  for (index_a, index_b) in pairs {
+    let (entity_a, layers_a, swept_a) = &colliders[index_a];
+    let (entity_b, layers_b, swept_b) = &colliders[index_b];
+    if !layers_a.interacts_with(layers_b) {
+      continue;
+    }
+    if let Some(time) = swept_a.hits(swept_b) {
+      let continuous = swept_a.continuous || swept_b.continuous;
+      sender.send(LayerCollision {
+        entity_a: *entity_a,
+        entity_b: *entity_b,
+        layers_a: *layers_a,
+        layers_b: *layers_b,
+        time_of_impact: continuous.then_some(time),
+        manifold: swept_a.manifold(swept_b),
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{
+    AxisAlignedBoundingBox, CircleCollider, CollisionLayersPlugin,
+    SweepAndPruneBroadphase,
+  };
+
+  const PLAYER: u32 = 1;
+  const ENEMY: u32 = 2;
+  const PICKUP: u32 = 4;
+
+  fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(CollisionLayersPlugin::new());
+    app
+  }
+
+  fn collisions(app: &mut App) -> Vec<LayerCollision> {
+    app.update();
+    app
+      .world_mut()
+      .resource_mut::<Events<LayerCollision>>()
+      .drain()
+      .collect()
+  }
+
+  fn pair(collision: &LayerCollision) -> (Entity, Entity) {
+    let (a, b) = (collision.entity_a, collision.entity_b);
+    (a.min(b), a.max(b))
+  }
+
+  #[test]
+  fn test_layers_filter_pairs() {
+    let mut app = test_app();
+    let mut spawn = |layers: CollisionLayers, x: f32| {
+      app
+        .world_mut()
+        .spawn((
+          Transform::from_xyz(x, 0.0, 0.0),
+          AxisAlignedBoundingBox::new(10.0, 10.0),
+          layers,
+        ))
+        .id()
+    };
+    // All four overlap each other
+    let player = spawn(CollisionLayers::new(PLAYER, ENEMY | PICKUP), 0.0);
+    let enemy = spawn(CollisionLayers::new(ENEMY, PLAYER), 2.0);
+    let pickup = spawn(CollisionLayers::new(PICKUP, PLAYER), 4.0);
+    spawn(CollisionLayers::new(ENEMY, CollisionLayers::NONE), 6.0);
+
+    let mut pairs: Vec<_> = collisions(&mut app).iter().map(pair).collect();
+    pairs.sort();
+    let mut expected = vec![
+      (player.min(enemy), player.max(enemy)),
+      (player.min(pickup), player.max(pickup)),
+    ];
+    expected.sort();
+    assert_eq!(pairs, expected);
+  }
+
+  #[test]
+  fn test_same_layer_pairs_reported_once() {
+    let mut app = test_app();
+    for x in [0.0, 5.0, 10.0] {
+      app.world_mut().spawn((
+        Transform::from_xyz(x, 0.0, 0.0),
+        CircleCollider::new(4.0),
+        CollisionLayers::default(),
+      ));
+    }
+    // Without layers an entity is ignored
+    app.world_mut().spawn((
+      Transform::default(),
+      CircleCollider::new(4.0),
+    ));
+
+    let found = collisions(&mut app);
+    assert_eq!(found.len(), 2);
+    assert_ne!(pair(&found[0]), pair(&found[1]));
+  }
+
+  #[test]
+  fn test_other_broadphase() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(
+      CollisionLayersPlugin::new()
+        .with_broadphase(SweepAndPruneBroadphase::new()),
+    );
+    for x in [0.0, 5.0, 10.0] {
+      app.world_mut().spawn((
+        Transform::from_xyz(x, 0.0, 0.0),
+        CircleCollider::new(4.0),
+        CollisionLayers::default(),
+      ));
+    }
+    assert_eq!(collisions(&mut app).len(), 2);
+  }
+
+  #[test]
+  fn test_interacts_with_needs_both_directions() {
+    let one_way = CollisionLayers::new(PLAYER, ENEMY);
+    let other = CollisionLayers::new(ENEMY, PICKUP);
+    assert!(!one_way.interacts_with(&other));
+    assert!(!other.interacts_with(&one_way));
+    let anything = CollisionLayers::new(ENEMY, CollisionLayers::ALL);
+    assert!(anything.interacts_with(&one_way));
+  }
+}
@@ -0,0 +1,130 @@
+use bevy::{prelude::*, utils::HashSet};
+use super::{ContactManifold, OnCollision};
+use std::marker::PhantomData;
+
+/// Sent on the first frame two entities touch.
+#[derive(Event)]
+pub struct CollisionStarted<A, B>
+where
+  A: Component,
+  B: Component,
+{
+  pub entity_a: Entity,
+  pub entity_b: Entity,
+  pub manifold: Option<ContactManifold>,
+  marker: PhantomData<(A, B)>,
+}
+
+/// Sent on every later frame that the two entities are still touching.
+#[derive(Event)]
+pub struct CollisionOngoing<A, B>
+where
+  A: Component,
+  B: Component,
+{
+  pub entity_a: Entity,
+  pub entity_b: Entity,
+  pub manifold: Option<ContactManifold>,
+  marker: PhantomData<(A, B)>,
+}
+
+/// Sent on the first frame two entities stop touching, including when
+/// one of them was despawned.
+#[derive(Event)]
+pub struct CollisionEnded<A, B>
+where
+  A: Component,
+  B: Component,
+{
+  pub entity_a: Entity,
+  pub entity_b: Entity,
+  marker: PhantomData<(A, B)>,
+}
+
+/// The pairs that were touching last frame.
+#[derive(Resource)]
+pub struct CollidingPairs<A, B>
+where
+  A: Component,
+  B: Component,
+{
+  pairs: HashSet<(Entity, Entity)>,
+  marker: PhantomData<(A, B)>,
+}
+
+impl<A, B> Default for CollidingPairs<A, B>
+where
+  A: Component,
+  B: Component,
+{
+  fn default() -> Self {
+    Self {
+      pairs: HashSet::new(),
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<A, B> CollidingPairs<A, B>
+where
+  A: Component,
+  B: Component,
+{
+  pub fn contains(&self, entity_a: Entity, entity_b: Entity) -> bool {
+    self.pairs.contains(&(entity_a, entity_b))
+  }
+
+  pub fn len(&self) -> usize {
+    self.pairs.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.pairs.is_empty()
+  }
+}
+
+/// Compares this frame's [`OnCollision`] events with last frame's and
+/// sends the matching started, ongoing and ended events. Must run after
+/// [`check_collisions`](super::check_collisions) in the same frame.
+pub fn track_collisions<A, B>(
+  mut collisions: EventReader<OnCollision<A, B>>,
+  mut colliding: ResMut<CollidingPairs<A, B>>,
+  mut started: EventWriter<CollisionStarted<A, B>>,
+  mut ongoing: EventWriter<CollisionOngoing<A, B>>,
+  mut ended: EventWriter<CollisionEnded<A, B>>,
+) where
+  A: Component,
+  B: Component,
+{
+  let mut current = HashSet::new();
+  for collision in collisions.read() {
+    let (entity_a, entity_b) = (collision.entity_a, collision.entity_b);
+    if !current.insert((entity_a, entity_b)) {
+      continue;
+    }
+    let manifold = collision.manifold.clone();
+    if colliding.pairs.contains(&(entity_a, entity_b)) {
+      ongoing.send(CollisionOngoing {
+        entity_a,
+        entity_b,
+        manifold,
+        marker: PhantomData,
+      });
+    } else {
+      started.send(CollisionStarted {
+        entity_a,
+        entity_b,
+        manifold,
+        marker: PhantomData,
+      });
+    }
+  }
+  for (entity_a, entity_b) in colliding.pairs.difference(&current) {
+    ended.send(CollisionEnded {
+      entity_a: *entity_a,
+      entity_b: *entity_b,
+      marker: PhantomData,
+    });
+  }
+  colliding.pairs = current;
+}
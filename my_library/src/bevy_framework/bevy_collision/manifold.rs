@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use super::shapes::Shape;
+
+/// Describes how two colliders touch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContactManifold {
+  /// Unit vector pointing from the second entity towards the first.
+  /// An `entity_a` resting on top of `entity_b` has a normal of
+  /// roughly `Vec2::Y`; one hitting `entity_b`'s left side has
+  /// roughly `-Vec2::X`.
+  pub normal: Vec2,
+  /// How far the two overlap along `normal`.
+  pub depth: f32,
+  /// Where the two touch, in world space. One or two points for most
+  /// shapes.
+  pub points: Vec<Vec2>,
+}
+
+impl ContactManifold {
+  /// The shortest move that pushes the first entity clear of the
+  /// second.
+  pub fn mtv(&self) -> Vec2 {
+    self.normal * self.depth
+  }
+}
+
+impl Shape {
+  /// Builds a [`ContactManifold`] if the two shapes touch. The normal
+  /// points from `other` towards `self`.
+  pub fn manifold(&self, other: &Shape) -> Option<ContactManifold> {
+    if let (Shape::Rect(a), Shape::Rect(b)) = (self, other) {
+      let shared = a.intersection(b)?;
+      let overlap = shared.max() - shared.min();
+      let between = a.center() - b.center();
+      let (min, max, mid) = (shared.min(), shared.max(), shared.center());
+      // Push out along the axis of least overlap; the contact is the
+      // line through the middle of the shared area across that axis.
+      let (normal, depth, points) = if overlap.x < overlap.y {
+        (
+          Vec2::new(sign(between.x), 0.0),
+          overlap.x,
+          vec![Vec2::new(mid.x, min.y), Vec2::new(mid.x, max.y)],
+        )
+      } else {
+        (
+          Vec2::new(0.0, sign(between.y)),
+          overlap.y,
+          vec![Vec2::new(min.x, mid.y), Vec2::new(max.x, mid.y)],
+        )
+      };
+      return Some(ContactManifold {
+        normal,
+        depth,
+        points: dedup(points),
+      });
+    }
+
+    let mtv = self.penetration(other)?;
+    let depth = mtv.length();
+    let between = self.bounds().center() - other.bounds().center();
+    let normal = mtv
+      .try_normalize()
+      .or_else(|| between.try_normalize())
+      .unwrap_or(Vec2::Y);
+
+    // Corners buried in the other shape, or failing that (round shapes,
+    // edge to edge) the middle of the deepest overlap.
+    let mut points: Vec<Vec2> = self
+      .vertices()
+      .into_iter()
+      .filter(|vertex| other.contains(*vertex))
+      .chain(
+        other
+          .vertices()
+          .into_iter()
+          .filter(|vertex| self.contains(*vertex)),
+      )
+      .collect();
+    if points.is_empty() {
+      points.push(self.support(-normal) + normal * depth / 2.0);
+    }
+    Some(ContactManifold {
+      normal,
+      depth,
+      points: dedup(points),
+    })
+  }
+
+  /// Is `point` inside the shape (edges included)?
+  pub fn contains(&self, point: Vec2) -> bool {
+    self.intersects(&Shape::Circle {
+      center: point,
+      radius: 0.0,
+    })
+  }
+
+  /// The point of the shape furthest along `direction`.
+  fn support(&self, direction: Vec2) -> Vec2 {
+    if let Some((a, b, radius)) = self.as_segment() {
+      let end = if a.dot(direction) >= b.dot(direction) { a } else { b };
+      return end + direction.normalize_or_zero() * radius;
+    }
+    self
+      .vertices()
+      .into_iter()
+      .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+      .unwrap_or_default()
+  }
+}
+
+fn sign(value: f32) -> f32 {
+  if value < 0.0 { -1.0 } else { 1.0 }
+}
+
+fn dedup(mut points: Vec<Vec2>) -> Vec<Vec2> {
+  points.dedup_by(|a, b| a.distance_squared(*b) < 0.0001);
+  points
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{CircleCollider, OrientedBox, Rect2D};
+
+  fn rect(x: f32, y: f32, w: f32, h: f32) -> Shape {
+    let half = Vec2::new(w, h) / 2.0;
+    let center = Vec2::new(x, y);
+    Shape::Rect(Rect2D::new(center - half, center + half))
+  }
+
+  #[test]
+  fn test_landed_on_top() {
+    let player = rect(0.0, 9.0, 10.0, 10.0);
+    let floor = rect(0.0, 0.0, 40.0, 10.0);
+    let manifold = player.manifold(&floor).unwrap();
+    assert_eq!(manifold.normal, Vec2::Y);
+    assert_eq!(manifold.depth, 1.0);
+    assert_eq!(
+      manifold.points,
+      vec![Vec2::new(-5.0, 4.5), Vec2::new(5.0, 4.5)]
+    );
+    assert_eq!(manifold.mtv(), Vec2::new(0.0, 1.0));
+  }
+
+  #[test]
+  fn test_hit_from_the_side() {
+    let player = rect(-8.0, 0.0, 10.0, 10.0);
+    let wall = rect(0.0, 0.0, 8.0, 40.0);
+    let manifold = player.manifold(&wall).unwrap();
+    assert_eq!(manifold.normal, -Vec2::X);
+    assert_eq!(manifold.depth, 1.0);
+    assert_eq!(manifold.points.len(), 2);
+    assert!(manifold.points.iter().all(|point| point.x == -3.5));
+  }
+
+  #[test]
+  fn test_separate_shapes_have_no_manifold() {
+    assert!(rect(0.0, 0.0, 10.0, 10.0)
+      .manifold(&rect(20.0, 0.0, 10.0, 10.0))
+      .is_none());
+  }
+
+  #[test]
+  fn test_circle_on_box() {
+    let ball = Shape::circle(Vec2::new(0.0, 7.0), &CircleCollider::new(4.0));
+    let manifold = ball.manifold(&rect(0.0, 0.0, 20.0, 10.0)).unwrap();
+    assert!((manifold.normal - Vec2::Y).length() < 0.001);
+    assert!((manifold.depth - 2.0).abs() < 0.001);
+    assert_eq!(manifold.points.len(), 1);
+    assert!((manifold.points[0] - Vec2::new(0.0, 4.0)).length() < 0.001);
+  }
+
+  #[test]
+  fn test_rotated_box_corner() {
+    // A diamond whose bottom corner pokes 1 unit into the floor
+    let half_diagonal = 50.0_f32.sqrt();
+    let transform = Transform::from_xyz(0.0, half_diagonal + 4.0, 0.0)
+      .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4));
+    let diamond =
+      Shape::polygon(&transform, &OrientedBox::new(10.0, 10.0).corners());
+    let manifold = diamond.manifold(&rect(0.0, 0.0, 40.0, 10.0)).unwrap();
+    assert!((manifold.normal - Vec2::Y).length() < 0.001);
+    assert!((manifold.depth - 1.0).abs() < 0.001);
+    assert_eq!(manifold.points.len(), 1);
+    assert!((manifold.points[0] - Vec2::new(0.0, 4.0)).length() < 0.001);
+  }
+}
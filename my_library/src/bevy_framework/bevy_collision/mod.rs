@@ -1,12 +1,179 @@
 mod aabb;
+mod broadphase;
+mod compound;
+mod dynamic_quadtree;
+mod layers;
+mod lifecycle;
+mod manifold;
+mod pixel_mask;
 mod rect2d;
+mod sat;
+mod sensor;
+mod shapes;
+mod spatial_index;
+mod spatial_query;
 mod static_quadtree;
+mod swept;
 pub use aabb::AxisAlignedBoundingBox;
+pub use broadphase::*;
+pub use compound::*;
+pub use dynamic_quadtree::*;
+pub use layers::*;
+pub use lifecycle::*;
+pub use manifold::*;
+pub use pixel_mask::*;
 pub use rect2d::Rect2D;
+pub use sensor::*;
+pub use shapes::*;
+pub use spatial_index::*;
+pub use spatial_query::*;
 pub use static_quadtree::*;
-use bevy::{prelude::*, utils::HashMap};
+pub use swept::*;
+use bevy::{
+  ecs::query::{QueryData, QueryFilter},
+  prelude::*,
+  tasks::{ComputeTaskPool, TaskPool},
+};
 use std::marker::PhantomData;
+use crate::PhysicsSet;
 
+/// Checks for collisions between entities marked `A` and entities
+/// marked `B` in [`PhysicsSet::Collide`], as part of a
+/// [`CollisionSet<A, B>`]. Sends an [`OnCollision`]
+/// every frame a pair touches, plus [`CollisionStarted`],
+/// [`CollisionOngoing`] and [`CollisionEnded`] as pairs come and go.
+///
+/// Candidates are found with a [`QuadTreeBroadphase`] covering a
+/// 1024x768 screen unless another [`Broadphase`] is chosen. Backends
+/// laid out over an area, like the [`DynamicQuadTreeBroadphase`], are
+/// kept centred on the [`Camera2d`] as it scrolls:
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use my_library::*;
+/// # #[derive(Component)] struct Ball;
+/// App::new().add_plugins(
+///   CollisionPlugin::<Ball, Ball>::default()
+///     .with_broadphase(SpatialHashBroadphase::new(32.0)),
+/// );
+/// ```
+pub struct CollisionPlugin<A, B, P = QuadTreeBroadphase> {
+  broadphase: P,
+  marker: PhantomData<fn() -> (A, B)>,
+}
+
+impl<A, B> Default for CollisionPlugin<A, B> {
+  fn default() -> Self {
+    Self {
+      broadphase: QuadTreeBroadphase::default(),
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<A, B, P> CollisionPlugin<A, B, P> {
+  pub fn with_broadphase<Q: Broadphase + Clone>(
+    self,
+    broadphase: Q,
+  ) -> CollisionPlugin<A, B, Q> {
+    CollisionPlugin {
+      broadphase,
+      marker: PhantomData,
+    }
+  }
+}
+
+impl<A, B, P> Plugin for CollisionPlugin<A, B, P>
+where
+  A: Component,
+  B: Component,
+  P: Broadphase + Clone,
+{
+  fn build(&self, app: &mut App) {
+    app.add_event::<OnCollision<A, B>>();
+    app.add_event::<CollisionStarted<A, B>>();
+    app.add_event::<CollisionOngoing<A, B>>();
+    app.add_event::<CollisionEnded<A, B>>();
+    app.init_resource::<CollidingPairs<A, B>>();
+    app.insert_resource(CollisionBroadphase::<(A, B)>::new(
+      self.broadphase.clone(),
+    ));
+    app.add_systems(
+      Update,
+      (
+        follow_camera::<(A, B)>,
+        check_collisions::<A, B>,
+        track_collisions::<A, B>,
+      )
+        .chain()
+        .in_set(CollisionSet::<A, B>::default()),
+    );
+    app.configure_sets(
+      Update,
+      CollisionSet::<A, B>::default().in_set(PhysicsSet::Collide),
+    );
+  }
+}
+
+/// The systems a [`CollisionPlugin<A, B>`] adds. Use it to order other
+/// systems around the checks, or to only run them some of the time:
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use my_library::*;
+/// # #[derive(Component)] struct Ball;
+/// # #[derive(Resource, PartialEq)] struct Checking(bool);
+/// App::new().configure_sets(
+///   Update,
+///   CollisionSet::<Ball, Ball>::default()
+///     .run_if(resource_exists_and_equals(Checking(true))),
+/// );
+/// ```
+#[derive(SystemSet)]
+pub struct CollisionSet<A, B>(PhantomData<fn() -> (A, B)>);
+
+impl<A, B> Default for CollisionSet<A, B> {
+  fn default() -> Self {
+    Self(PhantomData)
+  }
+}
+
+// Written out by hand so `A` and `B` needn't be `Clone`, `Eq` and so on
+impl<A, B> Clone for CollisionSet<A, B> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<A, B> Copy for CollisionSet<A, B> {}
+
+impl<A, B> PartialEq for CollisionSet<A, B> {
+  fn eq(&self, _other: &Self) -> bool {
+    true
+  }
+}
+
+impl<A, B> Eq for CollisionSet<A, B> {}
+
+impl<A, B> std::hash::Hash for CollisionSet<A, B> {
+  fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+impl<A, B> std::fmt::Debug for CollisionSet<A, B> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "CollisionSet<{}, {}>",
+      std::any::type_name::<A>(),
+      std::any::type_name::<B>()
+    )
+  }
+}
+
+/// Sent every frame an `A` and a `B` touch. Each unordered pair is
+/// sent at most once per frame: for `OnCollision<Ball, Ball>` the lower
+/// [`Entity`] is `entity_a`. A frame's events are sorted by
+/// `entity_a`, then `entity_b`.
 #[derive(Event)]
 pub struct OnCollision<A, B>
 where
@@ -15,45 +182,1034 @@ where
 {
   pub entity_a: Entity,
   pub entity_b: Entity,
+  /// When either entity has [`ContinuousCollision`], how far through
+  /// this frame's movement (0 to 1) the two first touched.
+  pub time_of_impact: Option<f32>,
+  /// Where and how deeply the two overlap at the end of the frame, or
+  /// `None` if they only touched partway through it.
+  pub manifold: Option<ContactManifold>,
   marker: PhantomData<(A, B)>,
 }
 
+/// Any entity with an [`AxisAlignedBoundingBox`], [`CircleCollider`],
+/// [`CapsuleCollider`], [`OrientedBox`], [`ConvexPolygonCollider`],
+/// [`CompoundCollider`] or [`PixelMask`] takes part in collision checks.
+pub type WithCollider = Or<(
+  With<AxisAlignedBoundingBox>,
+  With<CircleCollider>,
+  With<CapsuleCollider>,
+  With<OrientedBox>,
+  With<ConvexPolygonCollider>,
+  With<CompoundCollider>,
+  With<PixelMask>,
+)>;
+
+/// Checks every entity with [`CollisionLayers`] against every other in
+/// [`PhysicsSet::Collide`], sending a [`LayerCollision`] for each
+/// touching pair whose layers interact. One pass covers every kind of
+/// entity, instead of a [`CollisionPlugin`] per pair of marker types.
+pub struct CollisionLayersPlugin<P = QuadTreeBroadphase> {
+  broadphase: P,
+}
+
+impl CollisionLayersPlugin {
+  pub fn new() -> Self {
+    Self {
+      broadphase: QuadTreeBroadphase::default(),
+    }
+  }
+}
+
+impl Default for CollisionLayersPlugin {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<P> CollisionLayersPlugin<P> {
+  pub fn with_broadphase<Q: Broadphase + Clone>(
+    self,
+    broadphase: Q,
+  ) -> CollisionLayersPlugin<Q> {
+    CollisionLayersPlugin { broadphase }
+  }
+}
+
+impl<P: Broadphase + Clone> Plugin for CollisionLayersPlugin<P> {
+  fn build(&self, app: &mut App) {
+    app.add_event::<LayerCollision>();
+    app.insert_resource(CollisionBroadphase::<LayerCollision>::new(
+      self.broadphase.clone(),
+    ));
+    app.add_systems(
+      Update,
+      (follow_camera::<LayerCollision>, check_layer_collisions)
+        .chain()
+        .in_set(PhysicsSet::Collide),
+    );
+  }
+}
+
+/// Reports colliders entering and leaving [`Sensor`] volumes in
+/// [`PhysicsSet::Collide`].
+pub struct SensorPlugin<P = QuadTreeBroadphase> {
+  broadphase: P,
+}
+
+impl SensorPlugin {
+  pub fn new() -> Self {
+    Self {
+      broadphase: QuadTreeBroadphase::default(),
+    }
+  }
+}
+
+impl Default for SensorPlugin {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<P> SensorPlugin<P> {
+  pub fn with_broadphase<Q: Broadphase + Clone>(
+    self,
+    broadphase: Q,
+  ) -> SensorPlugin<Q> {
+    SensorPlugin { broadphase }
+  }
+}
+
+impl<P: Broadphase + Clone> Plugin for SensorPlugin<P> {
+  fn build(&self, app: &mut App) {
+    app.add_event::<SensorEntered>();
+    app.add_event::<SensorExited>();
+    app.init_resource::<SensorContacts>();
+    app.insert_resource(CollisionBroadphase::<Sensor>::new(
+      self.broadphase.clone(),
+    ));
+    app.add_systems(
+      Update,
+      (follow_camera::<Sensor>, check_sensors)
+        .chain()
+        .in_set(PhysicsSet::Collide),
+    );
+  }
+}
+
+/// Keeps a [`SpatialIndex`] of every collider up to date in
+/// [`PhysicsSet::Collide`], for region and nearest-neighbour queries.
+pub struct SpatialIndexPlugin {
+  tree: StaticQuadTree,
+}
+
+impl SpatialIndexPlugin {
+  /// Indexes a 1024x768 screen, split four levels deep.
+  pub fn new() -> Self {
+    Self {
+      tree: StaticQuadTree::new(Vec2::new(1024.0, 768.0), 4),
+    }
+  }
+
+  pub fn with_quad_tree(self, tree: StaticQuadTree) -> Self {
+    Self { tree }
+  }
+}
+
+impl Default for SpatialIndexPlugin {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Plugin for SpatialIndexPlugin {
+  fn build(&self, app: &mut App) {
+    app.insert_resource(SpatialIndex::new(self.tree.clone()));
+    app.add_systems(
+      Update,
+      update_spatial_index.in_set(PhysicsSet::Collide),
+    );
+  }
+}
+
+/// The parts of an entity that [`check_collisions`] reads.
+#[derive(QueryData)]
+pub struct ColliderQuery {
+  entity: Entity,
+  transform: &'static Transform,
+  global: Option<&'static GlobalTransform>,
+  parent: Option<&'static Parent>,
+  aabb: Option<&'static AxisAlignedBoundingBox>,
+  circle: Option<&'static CircleCollider>,
+  capsule: Option<&'static CapsuleCollider>,
+  oriented: Option<&'static OrientedBox>,
+  polygon: Option<&'static ConvexPolygonCollider>,
+  offset: Option<&'static ColliderOffset>,
+  compound: Option<&'static CompoundCollider>,
+  mask: Option<&'static PixelMask>,
+  sprite: Option<&'static Sprite>,
+  ccd: Option<&'static ContinuousCollision>,
+}
+
+/// Where an entity is in the world, as collision checks see it.
+/// Children use their [`GlobalTransform`] from the last time transforms
+/// were propagated; everything else uses its [`Transform`], so moves
+/// made earlier in the frame are seen straight away.
+pub(crate) fn world_transform(
+  transform: &Transform,
+  global: Option<&GlobalTransform>,
+  has_parent: bool,
+) -> Transform {
+  match global {
+    Some(global) if has_parent => global.compute_transform(),
+    _ => *transform,
+  }
+}
+
+impl ColliderQueryItem<'_> {
+  fn world_transform(&self) -> Transform {
+    world_transform(self.transform, self.global, self.parent.is_some())
+  }
+
+  /// The [`PixelMask`] frame the entity is showing, where it is now.
+  /// Warns if the sprite shows a frame the mask doesn't have, since the
+  /// entity then collides as if it had no mask.
+  fn placed_mask(&self) -> Option<PlacedMask> {
+    let mask = self.mask?;
+    let placed = PlacedMask::new(mask, self.sprite, &self.world_transform());
+    if placed.is_none() {
+      let index = self
+        .sprite
+        .and_then(|sprite| sprite.texture_atlas.as_ref())
+        .map_or(0, |atlas| atlas.index);
+      warn_once!(
+        "{} shows frame {index}, but its PixelMask only has {} frames; \
+         it collides as if it had no mask",
+        self.entity,
+        mask.frames().len()
+      );
+    }
+    placed
+  }
+
+  /// The collider's parts where the entity is now. If an entity has
+  /// more than one kind of single collider, the first in the order
+  /// above wins; a [`CompoundCollider`]'s parts are added to it.
+  fn shapes(&self) -> Vec<Shape> {
+    let transform = self.world_transform();
+    let offset = self.offset.map_or(Vec2::ZERO, |offset| offset.0);
+    let single = if let Some(aabb) = self.aabb {
+      Some(ColliderShape::Aabb(*aabb))
+    } else if let Some(circle) = self.circle {
+      Some(ColliderShape::Circle(*circle))
+    } else if let Some(capsule) = self.capsule {
+      Some(ColliderShape::Capsule(*capsule))
+    } else if let Some(oriented) = self.oriented {
+      Some(ColliderShape::Oriented(*oriented))
+    } else {
+      self.polygon.cloned().map(ColliderShape::Polygon)
+    };
+    let mut shapes: Vec<Shape> = single
+      .iter()
+      .map(|shape| shape.place(&transform, offset))
+      .collect();
+    if let Some(compound) = self.compound {
+      shapes.extend(
+        compound
+          .parts()
+          .iter()
+          .map(|(part, shape)| shape.place(&transform, offset + *part)),
+      );
+    }
+    if shapes.is_empty() {
+      let position = transform.translation.truncate();
+      shapes.push(Shape::Rect(Rect2D::new(position, position)));
+    }
+    shapes
+  }
+
+  /// The smallest box around the whole collider where the entity is
+  /// now.
+  fn bounds(&self) -> Rect2D {
+    if let Some(mask) = self.placed_mask() {
+      return mask.outline().bounds();
+    }
+    self
+      .shapes()
+      .iter()
+      .map(Shape::bounds)
+      .reduce(|a, b| a.union(&b))
+      .expect("A collider always has a part")
+  }
+}
+
+/// Where a collider's parts started this frame, and how far they
+/// moved. Entities without [`ContinuousCollision`] are treated as not
+/// moving. An entity with a [`PixelMask`] has its frame's outline as
+/// its only part, and keeps the mask where it ends the frame.
+#[derive(Clone)]
+struct Swept {
+  parts: Vec<Shape>,
+  mask: Option<PlacedMask>,
+  movement: Vec2,
+  continuous: bool,
+}
+
+impl Swept {
+  fn new(collider: &ColliderQueryItem) -> Self {
+    // In the same space as the parts and the recorded start
+    let end = collider.world_transform().translation.truncate();
+    let mask = collider.placed_mask();
+    let parts = match &mask {
+      Some(mask) => vec![mask.outline()],
+      None => collider.shapes(),
+    };
+    match collider.ccd.and_then(|ccd| ccd.previous) {
+      Some(previous) => Self {
+        parts: parts
+          .iter()
+          .map(|part| part.translated(previous - end))
+          .collect(),
+        mask,
+        movement: end - previous,
+        continuous: true,
+      },
+      None => Self {
+        parts,
+        mask,
+        movement: Vec2::ZERO,
+        continuous: false,
+      },
+    }
+  }
+
+  /// Everywhere the collider touched during the frame.
+  fn bounds(&self) -> Rect2D {
+    let start = self
+      .parts
+      .iter()
+      .map(Shape::bounds)
+      .reduce(|a, b| a.union(&b))
+      .expect("A collider always has a part");
+    let end = Rect2D::new(
+      start.min() + self.movement,
+      start.max() + self.movement,
+    );
+    start.union(&end)
+  }
+
+  /// The parts `time` (0 to 1) of the way through the frame.
+  fn at(&self, time: f32) -> Vec<Shape> {
+    self
+      .parts
+      .iter()
+      .map(|part| part.translated(self.movement * time))
+      .collect()
+  }
+
+  /// The mask `time` (0 to 1) of the way through the frame.
+  fn mask_at(&self, time: f32) -> Option<PlacedMask> {
+    let mask = self.mask.as_ref()?;
+    Some(mask.translated(self.movement * (time - 1.0)))
+  }
+
+  /// Returns the time of impact if the two colliders touch this frame:
+  /// the earliest time any part of one touches any part of the other.
+  /// If either has a [`PixelMask`], it is the earliest time after that
+  /// its solid pixels touch the other, tested at steps of about a pixel
+  /// so a moving mask can't skip past anything thin.
+  fn hits(&self, other: &Swept) -> Option<f32> {
+    let time = self
+      .parts
+      .iter()
+      .flat_map(|part| {
+        other.parts.iter().filter_map(move |other_part| {
+          part_hits(part, self.movement, other_part, other.movement)
+        })
+      })
+      .min_by(f32::total_cmp)?;
+    if self.mask.is_none() && other.mask.is_none() {
+      return Some(time);
+    }
+    let travel = (self.movement - other.movement).length() * (1.0 - time);
+    steps_from(time, travel.ceil() as u32)
+      .find(|time| self.pixels_touch(other, *time))
+  }
+
+  /// Do the solid pixels of either mask touch the other collider
+  /// `time` (0 to 1) of the way through the frame?
+  fn pixels_touch(&self, other: &Swept, time: f32) -> bool {
+    match (self.mask_at(time), other.mask_at(time)) {
+      (None, None) => true,
+      (Some(mask), Some(other_mask)) => mask.touches_mask(&other_mask),
+      (Some(mask), None) => mask.touches_shapes(&other.at(time)),
+      (None, Some(other_mask)) => other_mask.touches_shapes(&self.at(time)),
+    }
+  }
+
+  /// How the two colliders overlap at the end of the frame. With
+  /// several parts touching, the deepest contact wins.
+  fn manifold(&self, other: &Swept) -> Option<ContactManifold> {
+    let (parts, other_parts) = (self.at(1.0), other.at(1.0));
+    parts
+      .iter()
+      .flat_map(|part| {
+        other_parts
+          .iter()
+          .filter_map(move |other_part| part.manifold(other_part))
+      })
+      .max_by(|a, b| a.depth.total_cmp(&b.depth))
+  }
+}
+
+/// Returns the time of impact if two moving shapes touch this frame.
+/// Boxes are swept exactly. Other shapes start from the time their
+/// bounding boxes meet and are tested exactly at steps small enough
+/// that neither can skip past the other.
+fn part_hits(
+  shape: &Shape,
+  movement: Vec2,
+  other: &Shape,
+  other_movement: Vec2,
+) -> Option<f32> {
+  let (bounds, other_bounds) = (shape.bounds(), other.bounds());
+  let time = sweep(&bounds, movement, &other_bounds, other_movement)?;
+  if matches!((shape, other), (Shape::Rect(_), Shape::Rect(_))) {
+    return Some(time);
+  }
+  let size = (bounds.max() - bounds.min())
+    .min(other_bounds.max() - other_bounds.min())
+    .min_element()
+    .max(1.0);
+  let travel = (movement - other_movement).length() * (1.0 - time);
+  steps_from(time, (travel * 2.0 / size).ceil() as u32).find(|time| {
+    shape
+      .translated(movement * *time)
+      .intersects(&other.translated(other_movement * *time))
+  })
+}
+
+/// `steps + 1` evenly spaced times from `start` to the end of the frame,
+/// or just `start` if `steps` is zero.
+fn steps_from(start: f32, steps: u32) -> impl Iterator<Item = f32> {
+  (0..=steps).map(move |step| {
+    if steps == 0 {
+      start
+    } else {
+      start + (1.0 - start) * step as f32 / steps as f32
+    }
+  })
+}
+
+/// Sweeps every collider in `query`, spread across `pool`, in the
+/// query's order.
+fn sweep_all<F: QueryFilter>(
+  pool: &TaskPool,
+  query: &Query<ColliderQuery, F>,
+) -> Vec<(Entity, Swept)> {
+  let colliders: Vec<ColliderQueryItem> = query.iter().collect();
+  par_map(pool, &colliders, |collider| {
+    (collider.entity, Swept::new(collider))
+  })
+}
+
+/// Sweeps the colliders, builds the broadphase and tests the candidate
+/// pairs on the [`ComputeTaskPool`], then sends the collisions sorted
+/// by entity so the order doesn't depend on how the work was split up.
 pub fn check_collisions<A, B>(
-  quad_tree: Res<StaticQuadTree>,
-  query_a: Query<(Entity, &Transform, &AxisAlignedBoundingBox), With<A>>,
-  query_b: Query<(Entity, &Transform, &AxisAlignedBoundingBox), With<B>>,
+  mut broadphase: ResMut<CollisionBroadphase<(A, B)>>,
+  query_a: Query<ColliderQuery, (With<A>, WithCollider)>,
+  query_b: Query<ColliderQuery, (With<B>, WithCollider)>,
   mut sender: EventWriter<OnCollision<A, B>>,
 ) where
   A: Component,
   B: Component,
 {
-  let mut spatial_index: HashMap<usize, Vec<(Entity, Rect2D)>> =
-    HashMap::new();
-
-  query_b.iter().for_each(|(entity, transform, bbox)| {
-    let bbox = bbox.as_rect(transform.translation.truncate());
-    let in_node = quad_tree.smallest_node(&bbox);
-    if let Some(contents) = spatial_index.get_mut(&in_node) {
-      contents.push((entity, bbox));
-    } else {
-      spatial_index.insert(in_node, vec![(entity, bbox)]);
-    }
+  let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+  let colliders_b = sweep_all(pool, &query_b);
+  let bounds = par_map(pool, &colliders_b, |(_, swept)| swept.bounds());
+  broadphase.backend_mut().build_on(&bounds, pool);
+
+  let colliders_a = sweep_all(pool, &query_a);
+  let mut found = par_query(
+    broadphase.backend(),
+    pool,
+    &colliders_a,
+    |(_, swept_a)| swept_a.bounds(),
+    |(entity_a, swept_a), index| {
+      let (entity_b, swept_b) = &colliders_b[index];
+      if entity_a == entity_b {
+        return None;
+      }
+      // A pair where each entity is both an A and a B would be found
+      // from either end; only report it from the lower entity
+      if entity_b < entity_a
+        && query_a.contains(*entity_b)
+        && query_b.contains(*entity_a)
+      {
+        return None;
+      }
+      let time = swept_a.hits(swept_b)?;
+      let continuous = swept_a.continuous || swept_b.continuous;
+      Some(OnCollision {
+        entity_a: *entity_a,
+        entity_b: *entity_b,
+        time_of_impact: continuous.then_some(time),
+        manifold: swept_a.manifold(swept_b),
+        marker: PhantomData,
+      })
+    },
+  );
+  found.sort_unstable_by_key(|collision| {
+    (collision.entity_a, collision.entity_b)
   });
+  sender.send_batch(found);
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use rect2d::test::random_points;
+
+  #[derive(Component)]
+  struct Player;
+
+  #[derive(Component)]
+  struct Wall;
+
+  fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(CollisionPlugin::<Player, Wall>::default());
+    app
+  }
+
+  /// Runs a frame and returns how many started, ongoing and ended
+  /// events it sent.
+  fn frame(app: &mut App) -> (usize, usize, usize) {
+    app.update();
+    let world = app.world_mut();
+    (
+      world
+        .resource_mut::<Events<CollisionStarted<Player, Wall>>>()
+        .drain()
+        .count(),
+      world
+        .resource_mut::<Events<CollisionOngoing<Player, Wall>>>()
+        .drain()
+        .count(),
+      world
+        .resource_mut::<Events<CollisionEnded<Player, Wall>>>()
+        .drain()
+        .count(),
+    )
+  }
+
+  fn move_to(app: &mut App, entity: Entity, x: f32) {
+    app.world_mut().get_mut::<Transform>(entity).unwrap().translation.x = x;
+  }
+
+  #[test]
+  fn test_started_ongoing_ended() {
+    let mut app = test_app();
+    let player = app
+      .world_mut()
+      .spawn((
+        Player,
+        Transform::from_xyz(-100.0, 0.0, 0.0),
+        AxisAlignedBoundingBox::new(10.0, 10.0),
+      ))
+      .id();
+    app.world_mut().spawn((
+      Wall,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(10.0, 10.0),
+    ));
+
+    assert_eq!(frame(&mut app), (0, 0, 0));
+    move_to(&mut app, player, -5.0);
+    assert_eq!(frame(&mut app), (1, 0, 0));
+    assert_eq!(frame(&mut app), (0, 1, 0));
+    move_to(&mut app, player, 2.0);
+    assert_eq!(frame(&mut app), (0, 1, 0));
+    move_to(&mut app, player, 100.0);
+    assert_eq!(frame(&mut app), (0, 0, 1));
+    assert_eq!(frame(&mut app), (0, 0, 0));
+    move_to(&mut app, player, 0.0);
+    assert_eq!(frame(&mut app), (1, 0, 0));
+  }
+
+  #[test]
+  fn test_gated_set_skips_checks() {
+    #[derive(Resource, PartialEq)]
+    struct Checking(bool);
 
-  query_a.iter().for_each(|(entity_a, transform_a, bbox_a)| {
-    let bbox_a = bbox_a.as_rect(transform_a.translation.truncate());
-    for node in quad_tree.intersecting_nodes(&bbox_a) {
-      if let Some(contents) = spatial_index.get(&node) {
-        for (entity_b, bbox_b) in contents {
-          if entity_a != *entity_b && bbox_a.intersect(bbox_b) {
-            sender.send(OnCollision {
-              entity_a,
-              entity_b: *entity_b,
-              marker: PhantomData,
-            });
+    let mut app = test_app();
+    app.insert_resource(Checking(false));
+    app.configure_sets(
+      Update,
+      CollisionSet::<Player, Wall>::default()
+        .run_if(resource_equals(Checking(true))),
+    );
+    app.world_mut().spawn((
+      Player,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(10.0, 10.0),
+    ));
+    app.world_mut().spawn((
+      Wall,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(10.0, 10.0),
+    ));
+
+    assert_eq!(frame(&mut app), (0, 0, 0));
+    app.insert_resource(Checking(true));
+    assert_eq!(frame(&mut app), (1, 0, 0));
+  }
+
+  #[test]
+  fn test_despawn_ends_collision() {
+    let mut app = test_app();
+    app.world_mut().spawn((
+      Player,
+      Transform::default(),
+      CircleCollider::new(5.0),
+    ));
+    let wall = app
+      .world_mut()
+      .spawn((
+        Wall,
+        Transform::from_xyz(6.0, 0.0, 0.0),
+        AxisAlignedBoundingBox::new(4.0, 4.0),
+      ))
+      .id();
+
+    assert_eq!(frame(&mut app), (1, 0, 0));
+    assert_eq!(app.world().resource::<CollidingPairs<Player, Wall>>().len(), 1);
+    app.world_mut().despawn(wall);
+    assert_eq!(frame(&mut app), (0, 0, 1));
+    assert!(app.world().resource::<CollidingPairs<Player, Wall>>().is_empty());
+  }
+
+  #[test]
+  fn test_each_pair_tracked_separately() {
+    let mut app = test_app();
+    let player = app
+      .world_mut()
+      .spawn((
+        Player,
+        Transform::default(),
+        AxisAlignedBoundingBox::new(10.0, 10.0),
+      ))
+      .id();
+    for x in [-8.0, 8.0] {
+      app.world_mut().spawn((
+        Wall,
+        Transform::from_xyz(x, 0.0, 0.0),
+        AxisAlignedBoundingBox::new(10.0, 10.0),
+      ));
+    }
+
+    assert_eq!(frame(&mut app), (2, 0, 0));
+    move_to(&mut app, player, 4.0);
+    assert_eq!(frame(&mut app), (0, 1, 1));
+  }
+
+  #[test]
+  fn test_offset_and_scale_move_collider() {
+    let mut app = test_app();
+    let player = app
+      .world_mut()
+      .spawn((
+        Player,
+        Transform::default(),
+        AxisAlignedBoundingBox::new(10.0, 10.0),
+        ColliderOffset(Vec2::new(30.0, 0.0)),
+      ))
+      .id();
+    app.world_mut().spawn((
+      Wall,
+      Transform::from_xyz(30.0, 0.0, 0.0),
+      AxisAlignedBoundingBox::new(4.0, 4.0),
+    ));
+    assert_eq!(frame(&mut app), (1, 0, 0));
+
+    // Doubling the scale doubles the offset too, leaving the wall behind
+    app.world_mut().get_mut::<Transform>(player).unwrap().scale =
+      Vec3::splat(2.0);
+    assert_eq!(frame(&mut app), (0, 0, 1));
+  }
+
+  #[test]
+  fn test_compound_parts_collide() {
+    let mut app = test_app();
+    app.world_mut().spawn((
+      Player,
+      Transform::default(),
+      CompoundCollider::new()
+        .with(Vec2::new(-20.0, 0.0), AxisAlignedBoundingBox::new(10.0, 10.0))
+        .with(Vec2::new(20.0, 0.0), CircleCollider::new(5.0)),
+    ));
+    let wall = app
+      .world_mut()
+      .spawn((
+        Wall,
+        Transform::from_xyz(24.0, 0.0, 0.0),
+        AxisAlignedBoundingBox::new(4.0, 4.0),
+      ))
+      .id();
+    assert_eq!(frame(&mut app), (1, 0, 0));
+
+    // The gap between the parts doesn't collide
+    move_to(&mut app, wall, 0.0);
+    assert_eq!(frame(&mut app), (0, 0, 1));
+    move_to(&mut app, wall, -24.0);
+    assert_eq!(frame(&mut app), (1, 0, 0));
+  }
+
+  #[test]
+  fn test_child_collider_uses_global_transform() {
+    let mut app = test_app();
+    app.add_plugins(TransformPlugin);
+    let mut player = None;
+    app
+      .world_mut()
+      .spawn(Transform::from_xyz(100.0, 0.0, 0.0))
+      .with_children(|parent| {
+        player = Some(
+          parent
+            .spawn((
+              Player,
+              Transform::default(),
+              AxisAlignedBoundingBox::new(10.0, 10.0),
+            ))
+            .id(),
+        );
+      });
+    let wall = app
+      .world_mut()
+      .spawn((
+        Wall,
+        Transform::from_xyz(100.0, 0.0, 0.0),
+        AxisAlignedBoundingBox::new(10.0, 10.0),
+      ))
+      .id();
+
+    // The child has no offset of its own; only its parent puts it on
+    // the wall
+    assert_eq!(frame(&mut app), (1, 0, 0));
+    let colliding = app.world().resource::<CollidingPairs<Player, Wall>>();
+    assert!(colliding.contains(player.unwrap(), wall));
+  }
+
+  #[test]
+  fn test_child_sweep_stays_in_world_space() {
+    // Slides the child a long way along its parent; collision checks
+    // only see that once transforms are propagated
+    fn slide(mut query: Query<&mut Transform, With<Player>>) {
+      query.iter_mut().for_each(|mut transform| {
+        transform.translation.x += 2000.0;
+      });
+    }
+
+    let mut app = test_app();
+    app.add_plugins(TransformPlugin);
+    app.add_systems(
+      Update,
+      (record_sweep_start, slide)
+        .chain()
+        .before(PhysicsSet::Collide),
+    );
+    app
+      .world_mut()
+      .spawn(Transform::from_xyz(1000.0, 0.0, 0.0))
+      .with_children(|parent| {
+        parent.spawn((
+          Player,
+          Transform::default(),
+          AxisAlignedBoundingBox::new(10.0, 10.0),
+          ContinuousCollision::default(),
+        ));
+      });
+    // On the path the child's local move would trace if it were
+    // mistaken for a move in the world
+    app.world_mut().spawn((
+      Wall,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(10.0, 10.0),
+    ));
+
+    assert_eq!(frame(&mut app), (0, 0, 0));
+    assert_eq!(frame(&mut app), (0, 0, 0));
+  }
+
+  #[test]
+  fn test_pixel_mask_follows_sprite_frame() {
+    let mut app = test_app();
+    // Two 4x4 frames: the left column is solid, then the right one
+    let column = |solid_x: usize| {
+      MaskFrame::new(
+        UVec2::splat(4),
+        (0..16).map(|index| index % 4 == solid_x).collect(),
+      )
+    };
+    let player = app
+      .world_mut()
+      .spawn((
+        Player,
+        Transform::default(),
+        Sprite {
+          texture_atlas: Some(TextureAtlas {
+            layout: Handle::default(),
+            index: 0,
+          }),
+          ..default()
+        },
+        PixelMask::new(vec![column(0), column(3)]),
+      ))
+      .id();
+    // Overlaps the frame's box, but only the right column's pixels
+    app.world_mut().spawn((
+      Wall,
+      Transform::from_xyz(1.5, 0.0, 0.0),
+      AxisAlignedBoundingBox::new(2.0, 2.0),
+    ));
+    assert_eq!(frame(&mut app), (0, 0, 0));
+
+    let mut sprite = app.world_mut().get_mut::<Sprite>(player).unwrap();
+    sprite.texture_atlas.as_mut().unwrap().index = 1;
+    assert_eq!(frame(&mut app), (1, 0, 0));
+  }
+
+  #[test]
+  fn test_pixel_masks_touch_only_where_solid() {
+    let mut app = test_app();
+    // Solid only in the top-left and bottom-right quarters
+    let diagonal = PixelMask::new(vec![MaskFrame::new(
+      UVec2::splat(4),
+      (0..16).map(|index| (index % 4 < 2) == (index / 4 < 2)).collect(),
+    )]);
+    app.world_mut().spawn((Player, Transform::default(), diagonal.clone()));
+    let wall = app
+      .world_mut()
+      .spawn((Wall, Transform::from_xyz(2.0, 2.0, 0.0), diagonal))
+      .id();
+    // The other mask's empty bottom-left quarter sits on our empty
+    // top-right one
+    assert_eq!(frame(&mut app), (0, 0, 0));
+    move_to(&mut app, wall, 3.0);
+    assert_eq!(frame(&mut app), (0, 0, 0));
+    app.world_mut().get_mut::<Transform>(wall).unwrap().translation =
+      Vec3::new(1.0, -1.0, 0.0);
+    assert_eq!(frame(&mut app), (1, 0, 0));
+  }
+
+  #[test]
+  fn test_fast_pixel_mask_cannot_tunnel() {
+    fn dash(mut query: Query<&mut Transform, With<Player>>) {
+      query.iter_mut().for_each(|mut transform| {
+        transform.translation.x += 200.0;
+      });
+    }
+
+    let mut app = test_app();
+    app.add_systems(
+      Update,
+      (record_sweep_start, dash)
+        .chain()
+        .before(PhysicsSet::Collide),
+    );
+    // Solid only in its top two rows
+    let top_half = PixelMask::new(vec![MaskFrame::new(
+      UVec2::splat(4),
+      (0..16).map(|index| index / 4 < 2).collect(),
+    )]);
+    app.world_mut().spawn((
+      Player,
+      Transform::from_xyz(-100.0, 0.0, 0.0),
+      top_half,
+      ContinuousCollision::default(),
+    ));
+    // Thin walls in the way of the solid rows, and of the empty ones
+    let wall = app
+      .world_mut()
+      .spawn((
+        Wall,
+        Transform::from_xyz(0.0, 1.0, 0.0),
+        AxisAlignedBoundingBox::new(2.0, 2.0),
+      ))
+      .id();
+    app.world_mut().spawn((
+      Wall,
+      Transform::from_xyz(50.0, -1.25, 0.0),
+      AxisAlignedBoundingBox::new(2.0, 1.5),
+    ));
+
+    app.update();
+    let hits: Vec<_> = app
+      .world_mut()
+      .resource_mut::<Events<OnCollision<Player, Wall>>>()
+      .drain()
+      .map(|collision| (collision.entity_b, collision.time_of_impact))
+      .collect();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].0, wall);
+    // The mask's leading edge reaches the wall's near side at x = -1
+    let time = hits[0].1.unwrap();
+    assert!((time - 97.0 / 200.0).abs() < 0.01, "hit at {time}");
+  }
+
+  #[derive(Component)]
+  struct Ball;
+
+  /// Runs a frame and returns each ball pair reported, sorted.
+  fn ball_pairs(app: &mut App) -> Vec<(Entity, Entity)> {
+    app.update();
+    let mut pairs: Vec<_> = app
+      .world_mut()
+      .resource_mut::<Events<OnCollision<Ball, Ball>>>()
+      .drain()
+      .map(|collision| (collision.entity_a, collision.entity_b))
+      .collect();
+    pairs.sort();
+    pairs
+  }
+
+  #[test]
+  fn test_same_type_pairs_reported_once() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(CollisionPlugin::<Ball, Ball>::default());
+    let balls: Vec<Entity> = [0.0, 5.0, 10.0]
+      .into_iter()
+      .map(|x| {
+        app
+          .world_mut()
+          .spawn((
+            Ball,
+            Transform::from_xyz(x, 0.0, 0.0),
+            CircleCollider::new(4.0),
+          ))
+          .id()
+      })
+      .collect();
+
+    let mut expected = vec![
+      (balls[0].min(balls[1]), balls[0].max(balls[1])),
+      (balls[1].min(balls[2]), balls[1].max(balls[2])),
+    ];
+    expected.sort();
+    assert_eq!(ball_pairs(&mut app), expected);
+    // And again once the pairs are ongoing
+    assert_eq!(ball_pairs(&mut app), expected);
+  }
+
+  #[test]
+  fn test_collider_spanning_nodes_reported_once() {
+    let mut app = test_app();
+    app.world_mut().spawn((
+      Player,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(10.0, 10.0),
+    ));
+    // Covers most of the quadtree, so overlaps many of its nodes
+    app.world_mut().spawn((
+      Wall,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(900.0, 700.0),
+    ));
+
+    app.update();
+    let events = app
+      .world_mut()
+      .resource_mut::<Events<OnCollision<Player, Wall>>>()
+      .drain()
+      .count();
+    assert_eq!(events, 1);
+  }
+
+  #[test]
+  fn test_collisions_sent_in_entity_order() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(CollisionPlugin::<Ball, Ball>::default());
+    // A row of overlapping balls crossing many quadtree nodes, which
+    // the broadphase doesn't visit in entity order
+    for x in (0..200).rev() {
+      app.world_mut().spawn((
+        Ball,
+        Transform::from_xyz(x as f32 * 3.0 - 300.0, 0.0, 0.0),
+        CircleCollider::new(4.0),
+      ));
+    }
+    app.update();
+    let sent: Vec<(Entity, Entity)> = app
+      .world_mut()
+      .resource_mut::<Events<OnCollision<Ball, Ball>>>()
+      .drain()
+      .map(|collision| (collision.entity_a, collision.entity_b))
+      .collect();
+    assert!(!sent.is_empty());
+    assert!(sent.is_sorted());
+  }
+
+  #[test]
+  fn test_same_type_pairs_match_brute_force() {
+    let area = Rect2D::new(Vec2::new(-200.0, -150.0), Vec2::new(200.0, 150.0));
+    let positions = random_points(7, 300, area);
+
+    let backends: [fn(&mut App); 4] = [
+      |app| {
+        app.add_plugins(CollisionPlugin::<Ball, Ball>::default());
+      },
+      |app| {
+        app.add_plugins(
+          CollisionPlugin::<Ball, Ball>::default()
+            .with_broadphase(SpatialHashBroadphase::new(16.0)),
+        );
+      },
+      |app| {
+        app.add_plugins(
+          CollisionPlugin::<Ball, Ball>::default()
+            .with_broadphase(SweepAndPruneBroadphase::new()),
+        );
+      },
+      |app| {
+        app.add_plugins(
+          CollisionPlugin::<Ball, Ball>::default()
+            .with_broadphase(DynamicQuadTreeBroadphase::default()),
+        );
+      },
+    ];
+    for add_plugin in backends {
+      let mut app = App::new();
+      app.add_plugins(MinimalPlugins);
+      add_plugin(&mut app);
+      let balls: Vec<Entity> = positions
+        .iter()
+        .map(|position| {
+          app
+            .world_mut()
+            .spawn((
+              Ball,
+              Transform::from_translation(position.extend(0.0)),
+              CircleCollider::new(4.0),
+            ))
+            .id()
+        })
+        .collect();
+
+      let mut expected = Vec::new();
+      for a in 0..balls.len() {
+        for b in a + 1..balls.len() {
+          if positions[a].distance(positions[b]) <= 8.0 {
+            let (a, b) = (balls[a], balls[b]);
+            expected.push((a.min(b), a.max(b)));
           }
         }
       }
+      expected.sort();
+      assert!(!expected.is_empty());
+      assert_eq!(ball_pairs(&mut app), expected);
     }
-  });
-}
\ No newline at end of file
+  }
+}
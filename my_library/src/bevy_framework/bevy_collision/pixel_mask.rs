@@ -0,0 +1,314 @@
+use bevy::{math::Affine3A, prelude::*};
+use std::sync::Arc;
+use super::{OrientedBox, Shape};
+
+/// Pixels at least this opaque count as solid.
+pub const MASK_ALPHA_THRESHOLD: f32 = 0.5;
+
+/// The solid pixels of one sprite frame, row by row from the top left
+/// like the image itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaskFrame {
+  size: UVec2,
+  solid: Vec<bool>,
+}
+
+impl MaskFrame {
+  pub fn new(size: UVec2, solid: Vec<bool>) -> Self {
+    assert_eq!(
+      solid.len(),
+      (size.x * size.y) as usize,
+      "A mask needs one entry per pixel"
+    );
+    Self { size, solid }
+  }
+
+  /// Reads the alpha channel of `area` within `image`. Returns `None`
+  /// if the image's pixels can't be read, for example if it only
+  /// exists on the GPU.
+  pub fn from_image(image: &Image, area: URect) -> Option<Self> {
+    let size = area.size();
+    let mut solid = Vec::with_capacity((size.x * size.y) as usize);
+    for y in area.min.y..area.max.y {
+      for x in area.min.x..area.max.x {
+        let color = image.get_color_at(x, y).ok()?;
+        solid.push(color.alpha() >= MASK_ALPHA_THRESHOLD);
+      }
+    }
+    Some(Self { size, solid })
+  }
+
+  pub fn size(&self) -> UVec2 {
+    self.size
+  }
+
+  /// Is the pixel at `x`, `y` (from the top left) solid? Pixels outside
+  /// the frame never are.
+  pub fn is_solid(&self, x: i32, y: i32) -> bool {
+    let (width, height) = (self.size.x as i32, self.size.y as i32);
+    (0..width).contains(&x)
+      && (0..height).contains(&y)
+      && self.solid[(y * width + x) as usize]
+  }
+
+  /// Centres of the solid pixels, relative to the middle of the frame
+  /// with y pointing up, as a sprite is drawn.
+  fn solid_points(&self) -> impl Iterator<Item = Vec2> + '_ {
+    let half = self.size.as_vec2() / 2.0;
+    let width = self.size.x as usize;
+    self
+      .solid
+      .iter()
+      .enumerate()
+      .filter(|(_, solid)| **solid)
+      .map(move |(index, _)| {
+        let (x, y) = ((index % width) as f32, (index / width) as f32);
+        Vec2::new(x + 0.5 - half.x, half.y - y - 0.5)
+      })
+  }
+
+  /// Is the pixel under `point` (relative to the middle of the frame)
+  /// solid?
+  fn is_solid_at(&self, point: Vec2) -> bool {
+    let half = self.size.as_vec2() / 2.0;
+    self.is_solid(
+      (point.x + half.x).floor() as i32,
+      (half.y - point.y).floor() as i32,
+    )
+  }
+}
+
+/// A pixel-perfect collider built from a sprite's alpha channel, with
+/// one [`MaskFrame`] per texture atlas frame. The frame tested is the
+/// one the entity's [`Sprite`] is showing, so it follows its
+/// [`AnimationCycle`](crate::AnimationCycle).
+///
+/// The broadphase treats the entity as a box the size of the frame;
+/// only pairs whose boxes touch have their pixels compared, at the
+/// positions the entities end the frame in. With
+/// [`ContinuousCollision`](crate::ContinuousCollision) the pixels are
+/// also compared along the way, so a fast sprite can't skip through a
+/// thin one. Any other collider on the
+/// same entity is ignored. The sprite is assumed to be drawn centred
+/// and at its natural size.
+///
+/// Cloning is cheap: the frames are shared.
+#[derive(Component, Clone, Debug)]
+pub struct PixelMask {
+  frames: Arc<[MaskFrame]>,
+}
+
+impl PixelMask {
+  pub fn new(frames: Vec<MaskFrame>) -> Self {
+    assert!(!frames.is_empty(), "A pixel mask needs at least one frame");
+    Self {
+      frames: frames.into(),
+    }
+  }
+
+  /// A single frame covering the whole image.
+  pub fn from_image(image: &Image) -> Option<Self> {
+    let area = URect::from_corners(UVec2::ZERO, image.size());
+    Some(Self::new(vec![MaskFrame::from_image(image, area)?]))
+  }
+
+  /// One frame per texture in `layout`, in the same order.
+  pub fn from_atlas(
+    image: &Image,
+    layout: &TextureAtlasLayout,
+  ) -> Option<Self> {
+    let frames = layout
+      .textures
+      .iter()
+      .map(|area| MaskFrame::from_image(image, *area))
+      .collect::<Option<Vec<_>>>()?;
+    (!frames.is_empty()).then(|| Self::new(frames))
+  }
+
+  pub fn frames(&self) -> &[MaskFrame] {
+    &self.frames
+  }
+
+  pub fn frame(&self, index: usize) -> Option<&MaskFrame> {
+    self.frames.get(index)
+  }
+}
+
+/// A [`PixelMask`] frame placed in the world.
+#[derive(Clone, Debug)]
+pub(crate) struct PlacedMask {
+  mask: PixelMask,
+  index: usize,
+  flip: BVec2,
+  to_world: Affine3A,
+  to_local: Affine3A,
+}
+
+impl PlacedMask {
+  /// Places the frame `sprite` is showing at `transform`. Returns
+  /// `None` if the sprite shows a frame the mask doesn't have.
+  pub(crate) fn new(
+    mask: &PixelMask,
+    sprite: Option<&Sprite>,
+    transform: &Transform,
+  ) -> Option<Self> {
+    let index = sprite
+      .and_then(|sprite| sprite.texture_atlas.as_ref())
+      .map_or(0, |atlas| atlas.index);
+    mask.frame(index)?;
+    let flip = sprite.map_or(BVec2::FALSE, |sprite| {
+      BVec2::new(sprite.flip_x, sprite.flip_y)
+    });
+    let to_world = transform.compute_affine();
+    Some(Self {
+      mask: mask.clone(),
+      index,
+      flip,
+      to_world,
+      to_local: to_world.inverse(),
+    })
+  }
+
+  fn frame(&self) -> &MaskFrame {
+    &self.mask.frames[self.index]
+  }
+
+  /// The same frame moved by `offset` in the world.
+  pub(crate) fn translated(&self, offset: Vec2) -> Self {
+    let to_world =
+      Affine3A::from_translation(offset.extend(0.0)) * self.to_world;
+    Self {
+      to_world,
+      to_local: to_world.inverse(),
+      ..self.clone()
+    }
+  }
+
+  /// The frame's outline, for the broadphase.
+  pub(crate) fn outline(&self) -> Shape {
+    let size = self.frame().size().as_vec2();
+    let corners = OrientedBox::new(size.x, size.y).corners();
+    Shape::Polygon(
+      corners
+        .iter()
+        .map(|corner| self.to_world.transform_point3(corner.extend(0.0)))
+        .map(|point| point.truncate())
+        .collect(),
+    )
+  }
+
+  fn flipped(&self, point: Vec2) -> Vec2 {
+    Vec2::new(
+      if self.flip.x { -point.x } else { point.x },
+      if self.flip.y { -point.y } else { point.y },
+    )
+  }
+
+  /// Centres of the solid pixels in the world.
+  fn solid_points(&self) -> impl Iterator<Item = Vec2> + '_ {
+    self.frame().solid_points().map(|point| {
+      let point = self.flipped(point).extend(0.0);
+      self.to_world.transform_point3(point).truncate()
+    })
+  }
+
+  /// Is the pixel under the world `point` solid?
+  pub(crate) fn is_solid_at(&self, point: Vec2) -> bool {
+    let local = self.to_local.transform_point3(point.extend(0.0)).truncate();
+    self.frame().is_solid_at(self.flipped(local))
+  }
+
+  /// Does any solid pixel overlap a solid pixel of `other`?
+  pub(crate) fn touches_mask(&self, other: &PlacedMask) -> bool {
+    let bounds = other.outline().bounds();
+    self
+      .solid_points()
+      .filter(|point| bounds.contains(*point))
+      .any(|point| other.is_solid_at(point))
+  }
+
+  /// Does any solid pixel lie inside one of `shapes`?
+  pub(crate) fn touches_shapes(&self, shapes: &[Shape]) -> bool {
+    self
+      .solid_points()
+      .any(|point| shapes.iter().any(|shape| shape.contains(point)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use bevy::{
+    asset::RenderAssetUsages,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+  };
+
+  /// A `width` by `height` image, opaque where `solid` says so.
+  fn image(
+    width: u32,
+    height: u32,
+    solid: impl Fn(u32, u32) -> bool,
+  ) -> Image {
+    let mut data = Vec::new();
+    for y in 0..height {
+      for x in 0..width {
+        let alpha = if solid(x, y) { 255 } else { 0 };
+        data.extend([255, 255, 255, alpha]);
+      }
+    }
+    Image::new(
+      Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+      },
+      TextureDimension::D2,
+      data,
+      TextureFormat::Rgba8UnormSrgb,
+      RenderAssetUsages::default(),
+    )
+  }
+
+  #[test]
+  fn test_mask_from_alpha() {
+    // Only the top row is solid
+    let mask = PixelMask::from_image(&image(3, 2, |_, y| y == 0)).unwrap();
+    let frame = &mask.frames()[0];
+    assert_eq!(frame.size(), UVec2::new(3, 2));
+    assert!(frame.is_solid(2, 0));
+    assert!(!frame.is_solid(0, 1));
+    assert!(!frame.is_solid(3, 0));
+  }
+
+  #[test]
+  fn test_one_frame_per_atlas_texture() {
+    // Two 2x2 frames side by side; only the second has a solid pixel
+    let image = image(4, 2, |x, y| x == 3 && y == 1);
+    let layout =
+      TextureAtlasLayout::from_grid(UVec2::splat(2), 2, 1, None, None);
+    let mask = PixelMask::from_atlas(&image, &layout).unwrap();
+    assert_eq!(mask.frames().len(), 2);
+    assert!(!mask.frames()[0].solid.contains(&true));
+    assert!(mask.frames()[1].is_solid(1, 1));
+  }
+
+  #[test]
+  fn test_placed_mask_follows_transform_and_flip() {
+    // A 4x4 frame with only the top-left pixel solid
+    let mask = PixelMask::new(vec![MaskFrame::new(
+      UVec2::splat(4),
+      (0..16).map(|index| index == 0).collect(),
+    )]);
+    let transform = Transform::from_xyz(100.0, 0.0, 0.0);
+    let placed = PlacedMask::new(&mask, None, &transform).unwrap();
+    assert!(placed.is_solid_at(Vec2::new(98.5, 1.5)));
+    assert!(!placed.is_solid_at(Vec2::new(101.5, 1.5)));
+
+    let flipped = Sprite {
+      flip_x: true,
+      ..default()
+    };
+    let placed = PlacedMask::new(&mask, Some(&flipped), &transform).unwrap();
+    assert!(placed.is_solid_at(Vec2::new(101.5, 1.5)));
+  }
+}
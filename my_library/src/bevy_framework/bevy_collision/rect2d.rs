@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Rect2D {
   min: Vec2,
   max: Vec2,
@@ -18,6 +18,57 @@ impl Rect2D {
       && self.max.y >= other.min.y
   }
 
+  pub fn min(&self) -> Vec2 {
+    self.min
+  }
+
+  pub fn max(&self) -> Vec2 {
+    self.max
+  }
+
+  /// Is `point` inside the rectangle (edges included)?
+  pub fn contains(&self, point: Vec2) -> bool {
+    point.x >= self.min.x
+      && point.x <= self.max.x
+      && point.y >= self.min.y
+      && point.y <= self.max.y
+  }
+
+  /// The smallest rectangle containing both rectangles.
+  pub fn union(&self, other: &Self) -> Self {
+    Self::new(self.min.min(other.min), self.max.max(other.max))
+  }
+
+  pub fn center(&self) -> Vec2 {
+    (self.min + self.max) / 2.0
+  }
+
+  /// Width and height.
+  pub fn size(&self) -> Vec2 {
+    self.max - self.min
+  }
+
+  /// How far the two rectangles overlap on each axis, or `None` if
+  /// they don't overlap.
+  pub fn overlap(&self, other: &Self) -> Option<Vec2> {
+    let overlap = self.max.min(other.max) - self.min.max(other.min);
+    if overlap.x > 0.0 && overlap.y > 0.0 {
+      Some(overlap)
+    } else {
+      None
+    }
+  }
+
+  /// The area the two rectangles share, or `None` if they don't touch.
+  /// Rectangles that only touch give a zero-width intersection.
+  pub fn intersection(&self, other: &Self) -> Option<Self> {
+    self
+      .intersect(other)
+      .then(|| Self::new(self.min.max(other.min), self.max.min(other.max)))
+  }
+
+  /// Splits the rectangle into four equal parts that exactly cover it,
+  /// sharing only their edges.
   pub fn quadrants(&self) -> Vec<Self> {
     let center = (self.min + self.max) / 2.0;
     vec![
@@ -27,10 +78,145 @@ impl Rect2D {
         Vec2::new(self.max.x, center.y),
       ), // Top-Right
       Self::new(
-        Vec2::new(self.min.x, center.x),
+        Vec2::new(self.min.x, center.y),
         Vec2::new(center.x, self.max.y),
       ), // Bottom-left
       Self::new(center, self.max), // Bottom-right
     ]
   }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+  use super::*;
+  use crate::RandomNumberGenerator;
+  use proptest::prelude::*;
+  use std::ops::Range;
+
+  /// `count` points scattered over `area`. The same seed always gives
+  /// the same points.
+  pub(crate) fn random_points(
+    seed: u64,
+    count: usize,
+    area: Rect2D,
+  ) -> Vec<Vec2> {
+    // Only needs to be mutable without the "locking" feature
+    #[allow(unused_mut)]
+    let mut rng = RandomNumberGenerator::seeded(seed);
+    (0..count)
+      .map(|_| {
+        Vec2::new(
+          rng.range(area.min.x..area.max.x),
+          rng.range(area.min.y..area.max.y),
+        )
+      })
+      .collect()
+  }
+
+  /// `count` rectangles centred on points scattered over `area`, each
+  /// side between `sides.start` and `sides.end` long.
+  pub(crate) fn scattered_rects(
+    seed: u64,
+    count: usize,
+    area: Rect2D,
+    sides: Range<f32>,
+  ) -> Vec<Rect2D> {
+    #[allow(unused_mut)]
+    let mut rng = RandomNumberGenerator::seeded(seed);
+    random_points(seed, count, area)
+      .into_iter()
+      .map(|center| {
+        let half = Vec2::new(
+          rng.range(sides.clone()),
+          rng.range(sides.clone()),
+        ) / 2.0;
+        Rect2D::new(center - half, center + half)
+      })
+      .collect()
+  }
+
+  prop_compose! {
+    /// Any rectangle, from a point up to a large area.
+    pub(crate) fn any_rect()(
+      x in -2000.0_f32..2000.0,
+      y in -2000.0_f32..2000.0,
+      width in 0.0_f32..1500.0,
+      height in 0.0_f32..1500.0,
+    ) -> Rect2D {
+      Rect2D::new(Vec2::new(x, y), Vec2::new(x + width, y + height))
+    }
+  }
+
+  prop_compose! {
+    fn any_point()(x in -4000.0_f32..4000.0, y in -4000.0_f32..4000.0)
+      -> Vec2 {
+      Vec2::new(x, y)
+    }
+  }
+
+  #[test]
+  fn test_quadrants_of_a_wide_rect() {
+    let rect = Rect2D::new(Vec2::new(0.0, 0.0), Vec2::new(400.0, 100.0));
+    assert_eq!(
+      rect.quadrants(),
+      vec![
+        Rect2D::new(Vec2::new(0.0, 0.0), Vec2::new(200.0, 50.0)),
+        Rect2D::new(Vec2::new(200.0, 0.0), Vec2::new(400.0, 50.0)),
+        Rect2D::new(Vec2::new(0.0, 50.0), Vec2::new(200.0, 100.0)),
+        Rect2D::new(Vec2::new(200.0, 50.0), Vec2::new(400.0, 100.0)),
+      ]
+    );
+  }
+
+  proptest! {
+    #[test]
+    fn intersection_is_symmetric(a in any_rect(), b in any_rect()) {
+      prop_assert_eq!(a.intersect(&b), b.intersect(&a));
+      prop_assert_eq!(a.intersection(&b), b.intersection(&a));
+      prop_assert_eq!(a.intersection(&b).is_some(), a.intersect(&b));
+    }
+
+    #[test]
+    fn intersection_lies_in_both(a in any_rect(), b in any_rect()) {
+      if let Some(shared) = a.intersection(&b) {
+        for corner in [shared.min(), shared.max()] {
+          prop_assert!(a.contains(corner) && b.contains(corner));
+        }
+      }
+    }
+
+    #[test]
+    fn union_contains_both(a in any_rect(), b in any_rect()) {
+      let union = a.union(&b);
+      for corner in [a.min(), a.max(), b.min(), b.max()] {
+        prop_assert!(union.contains(corner));
+      }
+      prop_assert!(union.contains(a.center()));
+    }
+
+    #[test]
+    fn quadrants_tile_the_parent(rect in any_rect(), point in any_point()) {
+      let quadrants = rect.quadrants();
+      prop_assert_eq!(quadrants.len(), 4);
+      let union = quadrants.iter().skip(1).fold(quadrants[0], |all, quad| {
+        all.union(quad)
+      });
+      prop_assert_eq!(union, rect);
+      for quad in &quadrants {
+        prop_assert!(quad.min().cmple(quad.max()).all());
+        prop_assert!(rect.contains(quad.min()) && rect.contains(quad.max()));
+        let error = (quad.size() - rect.size() / 2.0).abs();
+        prop_assert!(error.max_element() < 0.001);
+      }
+      // Neighbours share edges but no area
+      for (i, a) in quadrants.iter().enumerate() {
+        for b in &quadrants[i + 1..] {
+          prop_assert_eq!(a.overlap(b), None);
+        }
+      }
+      // Every point of the parent is in a quadrant
+      let inside = point.clamp(rect.min(), rect.max());
+      prop_assert!(quadrants.iter().any(|quad| quad.contains(inside)));
+    }
+  }
+}
@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use super::shapes::{closest_point_on_segment, closest_points, Shape};
+
+impl Shape {
+  /// Tests the two shapes with the Separating Axis Theorem. If they
+  /// touch, returns the minimum translation vector: the shortest move
+  /// that pushes `self` clear of `other`.
+  pub fn penetration(&self, other: &Shape) -> Option<Vec2> {
+    let mut best: Option<Vec2> = None;
+    for axis in separating_axes(self, other) {
+      let (a_min, a_max) = self.project(axis);
+      let (b_min, b_max) = other.project(axis);
+      // How far `self` would have to move along +axis or -axis to stop
+      // overlapping.
+      let forward = b_max - a_min;
+      let backward = a_max - b_min;
+      if forward < 0.0 || backward < 0.0 {
+        return None;
+      }
+      let push = if forward < backward {
+        axis * forward
+      } else {
+        -axis * backward
+      };
+      if best.is_none_or(|best| push.length() < best.length()) {
+        best = Some(push);
+      }
+    }
+    best
+  }
+
+  /// The range covered by the shape along a unit `axis`.
+  fn project(&self, axis: Vec2) -> (f32, f32) {
+    if let Some((a, b, radius)) = self.as_segment() {
+      let (a, b) = (a.dot(axis), b.dot(axis));
+      return (a.min(b) - radius, a.max(b) + radius);
+    }
+    self.vertices().iter().fold(
+      (f32::INFINITY, f32::NEG_INFINITY),
+      |(min, max), vertex| {
+        let d = vertex.dot(axis);
+        (min.min(d), max.max(d))
+      },
+    )
+  }
+
+  /// Corners of boxes and polygons; empty for round shapes.
+  pub(crate) fn vertices(&self) -> Vec<Vec2> {
+    match self {
+      Shape::Rect(rect) => {
+        let (min, max) = (rect.min(), rect.max());
+        vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+      }
+      Shape::Polygon(points) => points.clone(),
+      Shape::Circle { .. } | Shape::Capsule { .. } => Vec::new(),
+    }
+  }
+
+  /// Directions that could separate this shape from `other`: each
+  /// edge's normal, the side of a capsule, and for rounded shapes the
+  /// line to the nearest corner (or nearest point of another rounded
+  /// shape).
+  fn own_axes(&self, other: &Shape, axes: &mut Vec<Vec2>) {
+    match self.as_segment() {
+      None => {
+        let vertices = self.vertices();
+        for (i, vertex) in vertices.iter().enumerate() {
+          let edge = vertices[(i + 1) % vertices.len()] - *vertex;
+          axes.push(edge.perp());
+        }
+      }
+      Some((a, b, _)) => {
+        axes.push((b - a).perp());
+        match other.as_segment() {
+          Some((c, d, _)) => {
+            let (p, q) = closest_points(a, b, c, d);
+            axes.push(q - p);
+          }
+          None => {
+            for vertex in other.vertices() {
+              axes.push(vertex - closest_point_on_segment(a, b, vertex));
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+fn separating_axes(a: &Shape, b: &Shape) -> Vec<Vec2> {
+  let mut axes = Vec::new();
+  a.own_axes(b, &mut axes);
+  b.own_axes(a, &mut axes);
+  let mut axes: Vec<Vec2> = axes
+    .into_iter()
+    .filter_map(|axis| axis.try_normalize())
+    .collect();
+  if axes.is_empty() {
+    // Two circles sharing a centre: any direction will do
+    axes.push(Vec2::Y);
+  }
+  axes
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{CircleCollider, ConvexPolygonCollider, OrientedBox, Rect2D};
+  use std::f32::consts::FRAC_PI_4;
+
+  fn rotated_box(x: f32, y: f32, angle: f32, size: f32) -> Shape {
+    let transform = Transform::from_xyz(x, y, 0.0)
+      .with_rotation(Quat::from_rotation_z(angle));
+    Shape::polygon(&transform, &OrientedBox::new(size, size).corners())
+  }
+
+  fn square(x: f32, y: f32, size: f32) -> Shape {
+    let half = Vec2::splat(size / 2.0);
+    let center = Vec2::new(x, y);
+    Shape::Rect(Rect2D::new(center - half, center + half))
+  }
+
+  #[test]
+  fn test_rotated_box_misses_where_aabb_would_hit() {
+    // A diamond (square turned 45 degrees) has corners at +/-7.07.
+    // Its bounding box overlaps the square, but the sloping edge doesn't.
+    let diamond = rotated_box(0.0, 0.0, FRAC_PI_4, 10.0);
+    let near_corner = square(6.5, 6.5, 4.0);
+    assert!(diamond.bounds().intersect(&near_corner.bounds()));
+    assert!(!diamond.intersects(&near_corner));
+    assert!(diamond.intersects(&square(9.0, 0.0, 4.0)));
+  }
+
+  #[test]
+  fn test_minimum_translation_vector() {
+    let a = square(0.0, 0.0, 10.0);
+    let b = rotated_box(12.0, 1.0, 0.0, 16.0);
+    let mtv = a.penetration(&b).unwrap();
+    assert!((mtv - Vec2::new(-1.0, 0.0)).length() < 0.001, "{mtv}");
+    // Moving by the MTV leaves them just touching
+    let moved = a.translated(mtv);
+    let again = moved.penetration(&b).unwrap();
+    assert!(again.length() < 0.001);
+  }
+
+  #[test]
+  fn test_polygon_and_circle() {
+    let triangle = Shape::polygon(
+      &Transform::default(),
+      ConvexPolygonCollider::new([
+        Vec2::new(0.0, 0.0),
+        Vec2::new(10.0, 0.0),
+        Vec2::new(0.0, 10.0),
+      ])
+      .points(),
+    );
+    let circle =
+      |x, y| Shape::circle(Vec2::new(x, y), &CircleCollider::new(2.0));
+    // Beyond the hypotenuse, inside the bounding box
+    assert!(!triangle.intersects(&circle(7.0, 7.0)));
+    assert!(triangle.intersects(&circle(5.0, 6.0)));
+    let mtv = circle(5.0, -1.0).penetration(&triangle).unwrap();
+    assert!((mtv - Vec2::new(0.0, -1.0)).length() < 0.001, "{mtv}");
+  }
+
+  #[test]
+  fn test_sat_agrees_with_distance_tests() {
+    let round = Shape::circle(Vec2::new(8.0, 8.0), &CircleCollider::new(4.0));
+    let box_shape = square(0.0, 0.0, 10.0);
+    assert_eq!(
+      round.penetration(&box_shape).is_some(),
+      round.intersects(&box_shape)
+    );
+    let touching =
+      Shape::circle(Vec2::new(7.0, 0.0), &CircleCollider::new(4.0));
+    let mtv = touching.penetration(&box_shape).unwrap();
+    assert!((mtv - Vec2::new(2.0, 0.0)).length() < 0.001);
+  }
+}
@@ -0,0 +1,176 @@
+use bevy::{prelude::*, utils::HashSet};
+use super::{
+  rect2d::Rect2D, ColliderQuery, CollisionBroadphase, Swept, WithCollider,
+};
+
+/// Turns a collider into a trigger volume: it reports entities that
+/// enter and leave it, but never blocks or pushes anything. Good for
+/// checkpoints, score zones and kill planes.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Sensor;
+
+/// A collider started overlapping a [`Sensor`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorEntered {
+  pub sensor: Entity,
+  pub visitor: Entity,
+}
+
+/// A collider stopped overlapping a [`Sensor`], or one of the two was
+/// despawned.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorExited {
+  pub sensor: Entity,
+  pub visitor: Entity,
+}
+
+/// Which colliders are inside which sensors.
+#[derive(Resource, Default, Debug)]
+pub struct SensorContacts {
+  contacts: HashSet<(Entity, Entity)>,
+}
+
+impl SensorContacts {
+  pub fn contains(&self, sensor: Entity, visitor: Entity) -> bool {
+    self.contacts.contains(&(sensor, visitor))
+  }
+
+  /// Everything currently inside `sensor`.
+  pub fn visitors(&self, sensor: Entity) -> impl Iterator<Item = Entity> + '_ {
+    self
+      .contacts
+      .iter()
+      .filter(move |(s, _)| *s == sensor)
+      .map(|(_, visitor)| *visitor)
+  }
+}
+
+/// Finds the colliders overlapping each [`Sensor`] and sends
+/// [`SensorEntered`] and [`SensorExited`] as that changes. Sensors
+/// don't detect each other.
+#[allow(clippy::type_complexity)]
+pub fn check_sensors(
+  mut broadphase: ResMut<CollisionBroadphase<Sensor>>,
+  sensors: Query<ColliderQuery, (With<Sensor>, WithCollider)>,
+  visitors: Query<ColliderQuery, (Without<Sensor>, WithCollider)>,
+  mut contacts: ResMut<SensorContacts>,
+  mut entered: EventWriter<SensorEntered>,
+  mut exited: EventWriter<SensorExited>,
+) {
+  let visitors: Vec<(Entity, Swept)> = visitors
+    .iter()
+    .map(|visitor| (visitor.entity, Swept::new(&visitor)))
+    .collect();
+  let bounds: Vec<Rect2D> =
+    visitors.iter().map(|(_, swept)| swept.bounds()).collect();
+  broadphase.backend_mut().build(&bounds);
+
+  let mut current = HashSet::new();
+  let mut nearby = Vec::new();
+  for sensor in sensors.iter() {
+    let swept_sensor = Swept::new(&sensor);
+    nearby.clear();
+    broadphase.backend().query(&swept_sensor.bounds(), &mut nearby);
+    for index in &nearby {
+      let (visitor, swept_visitor) = &visitors[*index];
+      if swept_sensor.hits(swept_visitor).is_some() {
+        current.insert((sensor.entity, *visitor));
+      }
+    }
+  }
+
+  for (sensor, visitor) in current.difference(&contacts.contacts) {
+    entered.send(SensorEntered {
+      sensor: *sensor,
+      visitor: *visitor,
+    });
+  }
+  for (sensor, visitor) in contacts.contacts.difference(&current) {
+    exited.send(SensorExited {
+      sensor: *sensor,
+      visitor: *visitor,
+    });
+  }
+  contacts.contacts = current;
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{AxisAlignedBoundingBox, CircleCollider, SensorPlugin};
+
+  fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(SensorPlugin::new());
+    app
+  }
+
+  fn frame(app: &mut App) -> (Vec<SensorEntered>, Vec<SensorExited>) {
+    app.update();
+    let world = app.world_mut();
+    (
+      world.resource_mut::<Events<SensorEntered>>().drain().collect(),
+      world.resource_mut::<Events<SensorExited>>().drain().collect(),
+    )
+  }
+
+  #[test]
+  fn test_enter_and_exit() {
+    let mut app = test_app();
+    let sensor = app
+      .world_mut()
+      .spawn((
+        Sensor,
+        Transform::default(),
+        AxisAlignedBoundingBox::new(20.0, 100.0),
+      ))
+      .id();
+    let visitor = app
+      .world_mut()
+      .spawn((Transform::from_xyz(-50.0, 0.0, 0.0), CircleCollider::new(5.0)))
+      .id();
+    let move_visitor = |app: &mut App, x: f32| {
+      app.world_mut().get_mut::<Transform>(visitor).unwrap().translation.x = x;
+    };
+
+    assert_eq!(frame(&mut app), (vec![], vec![]));
+    move_visitor(&mut app, -12.0);
+    let event = SensorEntered { sensor, visitor };
+    assert_eq!(frame(&mut app), (vec![event], vec![]));
+    move_visitor(&mut app, 0.0);
+    assert_eq!(frame(&mut app), (vec![], vec![]));
+    assert!(app.world().resource::<SensorContacts>().contains(sensor, visitor));
+    move_visitor(&mut app, 50.0);
+    let event = SensorExited { sensor, visitor };
+    assert_eq!(frame(&mut app), (vec![], vec![event]));
+  }
+
+  #[test]
+  fn test_sensors_ignore_each_other() {
+    let mut app = test_app();
+    let first = app
+      .world_mut()
+      .spawn((
+        Sensor,
+        Transform::default(),
+        AxisAlignedBoundingBox::new(20.0, 20.0),
+      ))
+      .id();
+    app.world_mut().spawn((
+      Sensor,
+      Transform::default(),
+      AxisAlignedBoundingBox::new(20.0, 20.0),
+    ));
+    let visitor = app
+      .world_mut()
+      .spawn((Transform::default(), AxisAlignedBoundingBox::new(4.0, 4.0)))
+      .id();
+
+    let (entered, _) = frame(&mut app);
+    assert_eq!(entered.len(), 2);
+    assert!(entered.iter().all(|event| event.visitor == visitor));
+    let contacts = app.world().resource::<SensorContacts>();
+    assert_eq!(contacts.visitors(first).collect::<Vec<_>>(), vec![visitor]);
+  }
+}
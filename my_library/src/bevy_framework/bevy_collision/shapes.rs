@@ -0,0 +1,361 @@
+use bevy::prelude::*;
+use super::rect2d::Rect2D;
+
+/// A round collider centred on the entity.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CircleCollider {
+  radius: f32,
+}
+
+impl CircleCollider {
+  pub fn new(radius: f32) -> Self {
+    Self { radius }
+  }
+
+  pub fn radius(&self) -> f32 {
+    self.radius
+  }
+}
+
+/// An upright pill shape: a vertical line `2 * half_length` long,
+/// centred on the entity, grown by `radius` in every direction.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CapsuleCollider {
+  half_length: f32,
+  radius: f32,
+}
+
+impl CapsuleCollider {
+  pub fn new(half_length: f32, radius: f32) -> Self {
+    Self {
+      half_length,
+      radius,
+    }
+  }
+
+  pub fn half_length(&self) -> f32 {
+    self.half_length
+  }
+
+  pub fn radius(&self) -> f32 {
+    self.radius
+  }
+}
+
+/// A box that turns and scales with its entity's [`Transform`], unlike
+/// [`AxisAlignedBoundingBox`](super::AxisAlignedBoundingBox).
+#[derive(Component, Clone, Copy, Debug)]
+pub struct OrientedBox {
+  half_size: Vec2,
+}
+
+impl OrientedBox {
+  pub fn new(width: f32, height: f32) -> Self {
+    Self {
+      half_size: Vec2::new(width / 2.0, height / 2.0),
+    }
+  }
+
+  pub fn size(&self) -> Vec2 {
+    self.half_size * 2.0
+  }
+
+  /// The box's corners before the entity's transform is applied.
+  pub fn corners(&self) -> [Vec2; 4] {
+    let Vec2 { x, y } = self.half_size;
+    [
+      Vec2::new(-x, -y),
+      Vec2::new(x, -y),
+      Vec2::new(x, y),
+      Vec2::new(-x, y),
+    ]
+  }
+}
+
+/// Any convex outline, given as points around the entity's origin.
+/// Like [`OrientedBox`] it follows the entity's rotation and scale.
+#[derive(Component, Clone, Debug)]
+pub struct ConvexPolygonCollider {
+  points: Vec<Vec2>,
+}
+
+impl ConvexPolygonCollider {
+  /// The points should run around the outline in order, either way
+  /// round. Concave outlines won't collide correctly.
+  pub fn new(points: impl IntoIterator<Item = Vec2>) -> Self {
+    let points: Vec<Vec2> = points.into_iter().collect();
+    assert!(points.len() >= 3, "A polygon needs at least three points");
+    Self { points }
+  }
+
+  pub fn points(&self) -> &[Vec2] {
+    &self.points
+  }
+}
+
+/// A collider placed in the world, ready for narrow-phase tests.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+  Rect(Rect2D),
+  Circle { center: Vec2, radius: f32 },
+  Capsule { a: Vec2, b: Vec2, radius: f32 },
+  /// A convex outline in world space.
+  Polygon(Vec<Vec2>),
+}
+
+impl Shape {
+  pub fn circle(center: Vec2, circle: &CircleCollider) -> Self {
+    Shape::Circle {
+      center,
+      radius: circle.radius,
+    }
+  }
+
+  pub fn capsule(center: Vec2, capsule: &CapsuleCollider) -> Self {
+    let offset = Vec2::new(0.0, capsule.half_length);
+    Shape::Capsule {
+      a: center - offset,
+      b: center + offset,
+      radius: capsule.radius,
+    }
+  }
+
+  /// Places `points` (relative to the entity) using the transform's
+  /// translation, rotation and scale.
+  pub fn polygon(transform: &Transform, points: &[Vec2]) -> Self {
+    Shape::Polygon(
+      points
+        .iter()
+        .map(|point| transform.transform_point(point.extend(0.0)).truncate())
+        .collect(),
+    )
+  }
+
+  /// The same shape moved by `offset`.
+  pub fn translated(&self, offset: Vec2) -> Self {
+    match self {
+      Shape::Rect(rect) => {
+        Shape::Rect(Rect2D::new(rect.min() + offset, rect.max() + offset))
+      }
+      Shape::Circle { center, radius } => Shape::Circle {
+        center: *center + offset,
+        radius: *radius,
+      },
+      Shape::Capsule { a, b, radius } => Shape::Capsule {
+        a: *a + offset,
+        b: *b + offset,
+        radius: *radius,
+      },
+      Shape::Polygon(points) => {
+        Shape::Polygon(points.iter().map(|point| *point + offset).collect())
+      }
+    }
+  }
+
+  /// The smallest axis-aligned rectangle containing the shape, used by
+  /// the broadphase.
+  pub fn bounds(&self) -> Rect2D {
+    match *self {
+      Shape::Rect(rect) => rect,
+      Shape::Polygon(ref points) => points.iter().skip(1).fold(
+        Rect2D::new(points[0], points[0]),
+        |bounds, point| bounds.union(&Rect2D::new(*point, *point)),
+      ),
+      Shape::Circle { center, radius } => {
+        Rect2D::new(center - Vec2::splat(radius), center + Vec2::splat(radius))
+      }
+      Shape::Capsule { a, b, radius } => Rect2D::new(
+        a.min(b) - Vec2::splat(radius),
+        a.max(b) + Vec2::splat(radius),
+      ),
+    }
+  }
+
+  /// Do the two shapes touch or overlap?
+  pub fn intersects(&self, other: &Shape) -> bool {
+    if matches!(self, Shape::Polygon(_)) || matches!(other, Shape::Polygon(_)) {
+      return self.penetration(other).is_some();
+    }
+    match (self.as_segment(), other.as_segment()) {
+      (None, None) => self.bounds().intersect(&other.bounds()),
+      (Some((a, b, radius)), None) => {
+        segment_rect_distance_sq(a, b, &other.bounds()) <= radius * radius
+      }
+      (None, Some((a, b, radius))) => {
+        segment_rect_distance_sq(a, b, &self.bounds()) <= radius * radius
+      }
+      (Some((a1, b1, r1)), Some((a2, b2, r2))) => {
+        segment_distance_sq(a1, b1, a2, b2) <= (r1 + r2) * (r1 + r2)
+      }
+    }
+  }
+
+  /// Circles and capsules are both a line segment grown by a radius;
+  /// a circle's segment has zero length.
+  pub(crate) fn as_segment(&self) -> Option<(Vec2, Vec2, f32)> {
+    match *self {
+      Shape::Rect(_) | Shape::Polygon(_) => None,
+      Shape::Circle { center, radius } => Some((center, center, radius)),
+      Shape::Capsule { a, b, radius } => Some((a, b, radius)),
+    }
+  }
+}
+
+/// Squared distance between segments `p1-q1` and `p2-q2`.
+fn segment_distance_sq(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> f32 {
+  let (c1, c2) = closest_points(p1, q1, p2, q2);
+  (c1 - c2).length_squared()
+}
+
+/// The closest point on segment `p-q` to `point`.
+pub(crate) fn closest_point_on_segment(p: Vec2, q: Vec2, point: Vec2) -> Vec2 {
+  let d = q - p;
+  let length_sq = d.length_squared();
+  if length_sq <= f32::EPSILON {
+    return p;
+  }
+  p + d * ((point - p).dot(d) / length_sq).clamp(0.0, 1.0)
+}
+
+/// The closest pair of points on segments `p1-q1` and `p2-q2` (after
+/// Ericson, "Real-Time Collision Detection", 5.1.9).
+pub(crate) fn closest_points(
+  p1: Vec2,
+  q1: Vec2,
+  p2: Vec2,
+  q2: Vec2,
+) -> (Vec2, Vec2) {
+  let d1 = q1 - p1;
+  let d2 = q2 - p2;
+  let r = p1 - p2;
+  let a = d1.length_squared();
+  let e = d2.length_squared();
+  let f = d2.dot(r);
+
+  let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+    (0.0, 0.0)
+  } else if a <= f32::EPSILON {
+    (0.0, (f / e).clamp(0.0, 1.0))
+  } else {
+    let c = d1.dot(r);
+    if e <= f32::EPSILON {
+      ((-c / a).clamp(0.0, 1.0), 0.0)
+    } else {
+      let b = d1.dot(d2);
+      let denom = a * e - b * b;
+      let s = if denom != 0.0 {
+        ((b * f - c * e) / denom).clamp(0.0, 1.0)
+      } else {
+        0.0
+      };
+      let t = (b * s + f) / e;
+      if t < 0.0 {
+        ((-c / a).clamp(0.0, 1.0), 0.0)
+      } else if t > 1.0 {
+        (((b - c) / a).clamp(0.0, 1.0), 1.0)
+      } else {
+        (s, t)
+      }
+    }
+  };
+  (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Squared distance from segment `a-b` to the rectangle; zero if they
+/// touch.
+fn segment_rect_distance_sq(a: Vec2, b: Vec2, rect: &Rect2D) -> f32 {
+  if rect.contains(a) || rect.contains(b) {
+    return 0.0;
+  }
+  let (min, max) = (rect.min(), rect.max());
+  let corners = [
+    min,
+    Vec2::new(max.x, min.y),
+    max,
+    Vec2::new(min.x, max.y),
+  ];
+  (0..4)
+    .map(|i| segment_distance_sq(a, b, corners[i], corners[(i + 1) % 4]))
+    .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn circle(x: f32, y: f32, radius: f32) -> Shape {
+    Shape::circle(Vec2::new(x, y), &CircleCollider::new(radius))
+  }
+
+  fn capsule(x: f32, y: f32, half_length: f32, radius: f32) -> Shape {
+    Shape::capsule(Vec2::new(x, y), &CapsuleCollider::new(half_length, radius))
+  }
+
+  fn rect(x: f32, y: f32, w: f32, h: f32) -> Shape {
+    let half = Vec2::new(w, h) / 2.0;
+    Shape::Rect(Rect2D::new(Vec2::new(x, y) - half, Vec2::new(x, y) + half))
+  }
+
+  #[test]
+  fn test_circle_circle() {
+    assert!(circle(0.0, 0.0, 4.0).intersects(&circle(7.0, 0.0, 4.0)));
+    assert!(!circle(0.0, 0.0, 4.0).intersects(&circle(6.0, 6.0, 4.0)));
+  }
+
+  #[test]
+  fn test_circle_rect() {
+    let square = rect(0.0, 0.0, 10.0, 10.0);
+    assert!(circle(8.0, 0.0, 4.0).intersects(&square));
+    assert!(square.intersects(&circle(8.0, 0.0, 4.0)));
+    // Near a corner the box's bounds overlap but the circle misses
+    assert!(!circle(8.0, 8.0, 4.0).intersects(&square));
+    // Entirely inside
+    assert!(circle(0.0, 0.0, 1.0).intersects(&square));
+  }
+
+  #[test]
+  fn test_capsule_circle() {
+    let pill = capsule(0.0, 0.0, 10.0, 2.0);
+    assert!(pill.intersects(&circle(0.0, 13.0, 2.0)));
+    assert!(pill.intersects(&circle(3.0, -5.0, 2.0)));
+    assert!(!pill.intersects(&circle(5.0, 0.0, 2.0)));
+  }
+
+  #[test]
+  fn test_capsule_capsule() {
+    let pill = capsule(0.0, 0.0, 10.0, 2.0);
+    assert!(pill.intersects(&capsule(3.0, 15.0, 10.0, 2.0)));
+    assert!(!pill.intersects(&capsule(5.0, 0.0, 10.0, 0.5)));
+  }
+
+  #[test]
+  fn test_capsule_rect() {
+    let pill = capsule(0.0, 0.0, 10.0, 2.0);
+    assert!(pill.intersects(&rect(3.0, 0.0, 4.0, 4.0)));
+    assert!(!pill.intersects(&rect(5.0, 0.0, 4.0, 4.0)));
+    // Crossing right through without either end inside
+    assert!(rect(0.0, 0.0, 40.0, 2.0).intersects(&pill));
+  }
+
+  #[test]
+  fn test_polygon_follows_transform() {
+    let transform = Transform::from_xyz(10.0, 0.0, 0.0)
+      .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2))
+      .with_scale(Vec3::splat(2.0));
+    let Shape::Polygon(points) =
+      Shape::polygon(&transform, &OrientedBox::new(4.0, 2.0).corners())
+    else {
+      panic!("expected a polygon");
+    };
+    let bounds = Shape::Polygon(points).bounds();
+    assert!((bounds.min() - Vec2::new(8.0, -4.0)).length() < 0.001);
+    assert!((bounds.max() - Vec2::new(12.0, 4.0)).length() < 0.001);
+  }
+
+  #[test]
+  fn test_bounds() {
+    let bounds = capsule(1.0, 1.0, 3.0, 1.0).bounds();
+    assert_eq!(bounds.min(), Vec2::new(0.0, -3.0));
+    assert_eq!(bounds.max(), Vec2::new(2.0, 5.0));
+  }
+}
@@ -0,0 +1,364 @@
+use bevy::{prelude::*, utils::HashMap};
+use super::{
+  rect2d::Rect2D, AxisAlignedBoundingBox, CapsuleCollider, CircleCollider,
+  ColliderOffset, ColliderQuery, CompoundCollider, ConvexPolygonCollider,
+  OrientedBox, PixelMask, StaticQuadTree, WithCollider,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+  node: usize,
+  bounds: Rect2D,
+  position: Vec2,
+}
+
+/// Remembers where every collider is, filed under a [`StaticQuadTree`],
+/// to answer "what's near here?" for AI, area-of-effect damage and
+/// homing. [`SpatialIndexPlugin`](super::SpatialIndexPlugin) keeps it
+/// up to date as entities move; it can also be filled by hand.
+///
+/// Region queries match anything whose bounding box touches the
+/// region. Nearest-neighbour queries measure to each entity's
+/// position.
+#[derive(Resource, Debug, Clone)]
+pub struct SpatialIndex {
+  tree: StaticQuadTree,
+  entries: HashMap<Entity, Entry>,
+  nodes: HashMap<usize, Vec<Entity>>,
+}
+
+impl Default for SpatialIndex {
+  /// A 1024x768 screen, split four levels deep.
+  fn default() -> Self {
+    Self::new(StaticQuadTree::new(Vec2::new(1024.0, 768.0), 4))
+  }
+}
+
+impl SpatialIndex {
+  pub fn new(tree: StaticQuadTree) -> Self {
+    Self {
+      tree,
+      entries: HashMap::new(),
+      nodes: HashMap::new(),
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn clear(&mut self) {
+    self.entries.clear();
+    self.nodes.clear();
+  }
+
+  /// Adds `entity` at `position` with the given bounds, or moves it
+  /// there if it's already indexed.
+  pub fn insert(&mut self, entity: Entity, position: Vec2, bounds: Rect2D) {
+    self.remove(entity);
+    // File it so both the bounds and the position can be found
+    let reach = bounds.union(&Rect2D::new(position, position));
+    let node = self.tree.smallest_node(&reach);
+    self.nodes.entry(node).or_default().push(entity);
+    self.entries.insert(
+      entity,
+      Entry {
+        node,
+        bounds,
+        position,
+      },
+    );
+  }
+
+  /// Forgets `entity`. Returns whether it was indexed.
+  pub fn remove(&mut self, entity: Entity) -> bool {
+    let Some(entry) = self.entries.remove(&entity) else {
+      return false;
+    };
+    if let Some(contents) = self.nodes.get_mut(&entry.node) {
+      contents.retain(|other| *other != entity);
+    }
+    true
+  }
+
+  /// Where `entity` was last indexed, and its bounds.
+  pub fn get(&self, entity: Entity) -> Option<(Vec2, Rect2D)> {
+    self
+      .entries
+      .get(&entity)
+      .map(|entry| (entry.position, entry.bounds))
+  }
+
+  /// Every entity whose bounds touch `area`, in no particular order.
+  pub fn in_rect(&self, area: &Rect2D) -> Vec<Entity> {
+    self
+      .candidates(area)
+      .filter(|entity| self.entries[entity].bounds.intersect(area))
+      .collect()
+  }
+
+  /// Every entity whose bounds touch the circle, in no particular
+  /// order.
+  pub fn in_circle(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+    let reach = Vec2::splat(radius);
+    let area = Rect2D::new(center - reach, center + reach);
+    self
+      .candidates(&area)
+      .filter(|entity| {
+        let bounds = self.entries[entity].bounds;
+        let closest = center.clamp(bounds.min(), bounds.max());
+        closest.distance_squared(center) <= radius * radius
+      })
+      .collect()
+  }
+
+  /// Up to `k` entities nearest to `point`, nearest first, with their
+  /// distances. Ties are broken by entity so results are repeatable.
+  pub fn nearest(&self, point: Vec2, k: usize) -> Vec<(Entity, f32)> {
+    if k == 0 || self.is_empty() {
+      return Vec::new();
+    }
+    // Search ever larger squares around the point until k entities are
+    // within reach or everything has been seen
+    let everything = self
+      .entries
+      .values()
+      .fold(Rect2D::new(point, point), |area, entry| {
+        area.union(&Rect2D::new(entry.position, entry.position))
+      });
+    let reach_all = (everything.min() - point)
+      .abs()
+      .max((everything.max() - point).abs())
+      .length();
+    // Start where k entities would be in reach if they were spread
+    // evenly
+    let size = everything.max() - everything.min();
+    let share = k as f32 / self.len() as f32;
+    let mut radius = (size.x * size.y * share / std::f32::consts::PI)
+      .sqrt()
+      .max(1.0);
+    loop {
+      let area =
+        Rect2D::new(point - Vec2::splat(radius), point + Vec2::splat(radius));
+      let mut found: Vec<(Entity, f32)> = self
+        .candidates(&area)
+        .map(|entity| (entity, self.entries[&entity].position.distance(point)))
+        .filter(|(_, distance)| *distance <= radius)
+        .collect();
+      if found.len() >= k || radius >= reach_all {
+        found.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        found.truncate(k);
+        return found;
+      }
+      radius *= 2.0;
+    }
+  }
+
+  /// Entities filed in the nodes touching `area`.
+  fn candidates(&self, area: &Rect2D) -> impl Iterator<Item = Entity> + '_ {
+    let mut nodes = self.tree.intersecting_nodes(area);
+    // Entities outside the tree's bounds all live in the root
+    nodes.insert(0);
+    nodes
+      .into_iter()
+      .filter_map(|node| self.nodes.get(&node))
+      .flatten()
+      .copied()
+  }
+}
+
+/// Colliders that moved, or whose shape was added or changed. A
+/// [`Sprite`] is included because it picks a [`PixelMask`]'s frame.
+type ColliderChanged = Or<(
+  Changed<Transform>,
+  Changed<GlobalTransform>,
+  Changed<AxisAlignedBoundingBox>,
+  Changed<CircleCollider>,
+  Changed<CapsuleCollider>,
+  Changed<OrientedBox>,
+  Changed<ConvexPolygonCollider>,
+  Changed<CompoundCollider>,
+  Changed<ColliderOffset>,
+  Changed<PixelMask>,
+  Changed<Sprite>,
+)>;
+
+/// Re-indexes colliders that moved or whose shape was added or changed,
+/// and drops entities that were despawned or lost their collider.
+pub fn update_spatial_index(
+  mut index: ResMut<SpatialIndex>,
+  moved: Query<ColliderQuery, (WithCollider, ColliderChanged)>,
+  colliders: Query<(), WithCollider>,
+) {
+  let gone: Vec<Entity> = index
+    .entries
+    .keys()
+    .filter(|entity| !colliders.contains(**entity))
+    .copied()
+    .collect();
+  for entity in gone {
+    index.remove(entity);
+  }
+  for collider in moved.iter() {
+    let position = collider.world_transform().translation.truncate();
+    index.insert(collider.entity, position, collider.bounds());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{AxisAlignedBoundingBox, CircleCollider, SpatialIndexPlugin};
+  use crate::bevy_framework::bevy_collision::rect2d::test::scattered_rects;
+
+  fn entity(index: u32) -> Entity {
+    Entity::from_raw(index)
+  }
+
+  fn square(center: Vec2, half: f32) -> Rect2D {
+    Rect2D::new(center - Vec2::splat(half), center + Vec2::splat(half))
+  }
+
+  /// A few hundred entities, some off screen.
+  fn scattered() -> (SpatialIndex, Vec<(Entity, Vec2, Rect2D)>) {
+    let area = Rect2D::new(Vec2::new(-700.0, -500.0), Vec2::new(700.0, 500.0));
+    let mut index = SpatialIndex::default();
+    let entities: Vec<_> = scattered_rects(3, 300, area, 2.0..42.0)
+      .into_iter()
+      .enumerate()
+      .map(|(i, bounds)| {
+        let i = i as u32;
+        index.insert(entity(i), bounds.center(), bounds);
+        (entity(i), bounds.center(), bounds)
+      })
+      .collect();
+    (index, entities)
+  }
+
+  #[test]
+  fn test_insert_moves_and_remove_forgets() {
+    let mut index = SpatialIndex::default();
+    index.insert(entity(1), Vec2::ZERO, square(Vec2::ZERO, 5.0));
+    let moved = Vec2::splat(300.0);
+    index.insert(entity(1), moved, square(moved, 5.0));
+    assert_eq!(index.len(), 1);
+    assert!(index.in_rect(&square(Vec2::ZERO, 10.0)).is_empty());
+    assert_eq!(index.in_rect(&square(moved, 1.0)), vec![entity(1)]);
+    assert!(index.remove(entity(1)));
+    assert!(!index.remove(entity(1)));
+    assert!(index.is_empty());
+  }
+
+  #[test]
+  fn test_region_queries_match_brute_force() {
+    let (index, entities) = scattered();
+    let area =
+      Rect2D::new(Vec2::new(-200.0, -50.0), Vec2::new(150.0, 300.0));
+    let mut found = index.in_rect(&area);
+    found.sort();
+    let expected: Vec<Entity> = entities
+      .iter()
+      .filter(|(_, _, bounds)| bounds.intersect(&area))
+      .map(|(entity, _, _)| *entity)
+      .collect();
+    assert_eq!(found, expected);
+
+    let (center, radius) = (Vec2::new(100.0, -100.0), 120.0);
+    let mut found = index.in_circle(center, radius);
+    found.sort();
+    let expected: Vec<Entity> = entities
+      .iter()
+      .filter(|(_, _, bounds)| {
+        let closest = center.clamp(bounds.min(), bounds.max());
+        closest.distance(center) <= radius
+      })
+      .map(|(entity, _, _)| *entity)
+      .collect();
+    assert!(!expected.is_empty());
+    assert_eq!(found, expected);
+  }
+
+  #[test]
+  fn test_nearest_matches_brute_force() {
+    let (index, entities) = scattered();
+    let points = [Vec2::ZERO, Vec2::new(450.0, -300.0), Vec2::splat(5000.0)];
+    for point in points {
+      let mut expected: Vec<(Entity, f32)> = entities
+        .iter()
+        .map(|(entity, position, _)| (*entity, position.distance(point)))
+        .collect();
+      expected.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+      expected.truncate(5);
+      assert_eq!(index.nearest(point, 5), expected);
+    }
+    assert_eq!(index.nearest(Vec2::ZERO, 1000).len(), entities.len());
+    assert!(index.nearest(Vec2::ZERO, 0).is_empty());
+  }
+
+  #[test]
+  fn test_plugin_follows_transforms() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(SpatialIndexPlugin::new());
+    let near = app
+      .world_mut()
+      .spawn((Transform::default(), CircleCollider::new(4.0)))
+      .id();
+    let far = app
+      .world_mut()
+      .spawn((
+        Transform::from_xyz(200.0, 0.0, 0.0),
+        AxisAlignedBoundingBox::new(10.0, 10.0),
+      ))
+      .id();
+    // Not a collider, so never indexed
+    app.world_mut().spawn(Transform::default());
+
+    app.update();
+    let index = app.world().resource::<SpatialIndex>();
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.in_circle(Vec2::ZERO, 10.0), vec![near]);
+
+    app.world_mut().get_mut::<Transform>(far).unwrap().translation.x = 5.0;
+    app.update();
+    let index = app.world().resource::<SpatialIndex>();
+    let nearest = index.nearest(Vec2::new(6.0, 0.0), 2);
+    assert_eq!(nearest[0], (far, 1.0));
+
+    app.world_mut().despawn(near);
+    app.update();
+    let index = app.world().resource::<SpatialIndex>();
+    assert_eq!(index.nearest(Vec2::ZERO, 2), vec![(far, 5.0)]);
+  }
+
+  #[test]
+  fn test_plugin_follows_colliders_on_still_entities() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(SpatialIndexPlugin::new());
+    let entity = app.world_mut().spawn(Transform::default()).id();
+    app.update();
+    assert!(app.world().resource::<SpatialIndex>().is_empty());
+
+    // Neither of these moves the entity
+    app
+      .world_mut()
+      .entity_mut(entity)
+      .insert(AxisAlignedBoundingBox::new(10.0, 10.0));
+    app.update();
+    let index = app.world().resource::<SpatialIndex>();
+    assert_eq!(index.in_circle(Vec2::new(4.0, 0.0), 0.5), vec![entity]);
+
+    app
+      .world_mut()
+      .entity_mut(entity)
+      .insert(AxisAlignedBoundingBox::new(40.0, 10.0));
+    app.update();
+    let index = app.world().resource::<SpatialIndex>();
+    assert_eq!(index.in_circle(Vec2::new(18.0, 0.0), 0.5), vec![entity]);
+  }
+}
@@ -0,0 +1,389 @@
+use bevy::{
+  ecs::{
+    component::Tick,
+    query::QueryFilter,
+    system::{SystemChangeTick, SystemParam},
+  },
+  prelude::*,
+};
+use std::sync::Mutex;
+use super::{
+  part_hits, rect2d::Rect2D, sweep, sweep_with_normal,
+  AxisAlignedBoundingBox, Broadphase, ColliderQuery, ColliderQueryItem,
+  QuadTreeBroadphase, Shape, SpatialIndex, WithCollider,
+};
+
+/// The result of a ray or shape cast.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CastHit {
+  pub entity: Entity,
+  /// How far along the cast direction the hit happened
+  pub distance: f32,
+  /// Where the ray (or the centre of the cast shape) was on impact
+  pub point: Vec2,
+  /// The outward normal of the face that was hit. Zero if the cast
+  /// started inside the collider.
+  pub normal: Vec2,
+}
+
+/// Answers "what's over there?" questions about every kind of
+/// collider. Narrow the search with a filter, for example
+/// `SpatialQuery<With<Obstacle>>` or `SpatialQuery<Without<Player>>`.
+///
+/// When a [`SpatialIndex`] resource exists (see
+/// [`SpatialIndexPlugin`](super::SpatialIndexPlugin)) it is used to
+/// skip colliders far from the query. Colliders are then found where
+/// the index last saw them, but tested where they are now. Without one,
+/// the matching colliders are filed into a quadtree the first time the
+/// system queries, and that tree answers the rest of the system's
+/// queries in the same run.
+///
+/// Boxes are hit exactly and other shapes to within a tiny fraction of
+/// a unit. A [`PixelMask`](super::PixelMask) is cast against its
+/// frame's outline, but point queries test its pixels.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's, F: QueryFilter + 'static = ()> {
+  index: Option<Res<'w, SpatialIndex>>,
+  colliders: Query<'w, 's, ColliderQuery, (F, WithCollider)>,
+  fallback: Local<'s, Mutex<Fallback>>,
+  ticks: SystemChangeTick,
+}
+
+/// The quadtree used when there's no [`SpatialIndex`], and the run of
+/// the system it was built in.
+#[derive(Default)]
+struct Fallback {
+  built: Option<Tick>,
+  tree: QuadTreeBroadphase,
+  entities: Vec<Entity>,
+}
+
+impl<F: QueryFilter + 'static> SpatialQuery<'_, '_, F> {
+  /// Every collider whose bounds might touch `area`.
+  fn candidates(&self, area: &Rect2D) -> Vec<ColliderQueryItem<'_>> {
+    match &self.index {
+      Some(index) => index
+        .in_rect(area)
+        .into_iter()
+        .filter_map(|entity| self.colliders.get(entity).ok())
+        .collect(),
+      None => {
+        let mut fallback = self.fallback.lock().unwrap();
+        let run = self.ticks.this_run();
+        if fallback.built != Some(run) {
+          let (entities, bounds): (Vec<Entity>, Vec<Rect2D>) = self
+            .colliders
+            .iter()
+            .map(|collider| (collider.entity, collider.bounds()))
+            .unzip();
+          fallback.tree.build(&bounds);
+          fallback.entities = entities;
+          fallback.built = Some(run);
+        }
+        let mut found = Vec::new();
+        fallback.tree.query(area, &mut found);
+        found
+          .into_iter()
+          .filter_map(|index| self.colliders.get(fallback.entities[index]).ok())
+          .collect()
+      }
+    }
+  }
+
+  /// Every hit of a box of `size` swept from `origin` along
+  /// `direction` for up to `max_distance`, nearest first.
+  fn cast(
+    &self,
+    size: Vec2,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+  ) -> Vec<CastHit> {
+    let direction = direction.normalize_or_zero();
+    let movement = direction * max_distance;
+    let half = size / 2.0;
+    let start = Rect2D::new(origin - half, origin + half);
+    let end = Rect2D::new(start.min() + movement, start.max() + movement);
+    let shape = Shape::Rect(start);
+
+    let mut hits: Vec<CastHit> = self
+      .candidates(&start.union(&end))
+      .iter()
+      .filter_map(|collider| {
+        let (time, normal) = cast_parts(collider)
+          .iter()
+          .filter_map(|part| cast_hit(&shape, movement, part))
+          .min_by(|a, b| a.0.total_cmp(&b.0))?;
+        Some(CastHit {
+          entity: collider.entity,
+          distance: time * max_distance,
+          point: origin + movement * time,
+          normal,
+        })
+      })
+      .collect();
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    hits
+  }
+
+  /// The nearest collider hit by a ray, if any.
+  pub fn cast_ray(
+    &self,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+  ) -> Option<CastHit> {
+    self.cast_ray_all(origin, direction, max_distance).first().copied()
+  }
+
+  /// Every collider hit by a ray, nearest first.
+  pub fn cast_ray_all(
+    &self,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+  ) -> Vec<CastHit> {
+    self.cast(Vec2::ZERO, origin, direction, max_distance)
+  }
+
+  /// The nearest collider hit by sliding `shape` (centred on `origin`)
+  /// along `direction`, if any.
+  pub fn cast_aabb(
+    &self,
+    shape: &AxisAlignedBoundingBox,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: f32,
+  ) -> Option<CastHit> {
+    self
+      .cast(shape.size(), origin, direction, max_distance)
+      .first()
+      .copied()
+  }
+
+  /// Every collider containing `point`.
+  pub fn point(&self, point: Vec2) -> Vec<Entity> {
+    self
+      .candidates(&Rect2D::new(point, point))
+      .iter()
+      .filter(|collider| match collider.placed_mask() {
+        Some(mask) => mask.is_solid_at(point),
+        None => collider.shapes().iter().any(|shape| shape.contains(point)),
+      })
+      .map(|collider| collider.entity)
+      .collect()
+  }
+}
+
+/// The shapes a cast can hit: a pixel mask's outline, or the
+/// collider's parts.
+fn cast_parts(collider: &ColliderQueryItem) -> Vec<Shape> {
+  match collider.placed_mask() {
+    Some(mask) => vec![mask.outline()],
+    None => collider.shapes(),
+  }
+}
+
+/// When (0 to 1) `shape` moving by `movement` first touches `part`,
+/// and the outward normal of `part` there.
+fn cast_hit(
+  shape: &Shape,
+  movement: Vec2,
+  part: &Shape,
+) -> Option<(f32, Vec2)> {
+  if let (Shape::Rect(rect), Shape::Rect(part)) = (shape, part) {
+    return sweep_with_normal(rect, movement, part, Vec2::ZERO);
+  }
+  if shape.intersects(part) {
+    return Some((0.0, Vec2::ZERO));
+  }
+  // Home in on the moment they meet, between when their boxes meet and
+  // the first step found touching
+  let mut after = part_hits(shape, movement, part, Vec2::ZERO)?;
+  let mut before =
+    sweep(&shape.bounds(), movement, &part.bounds(), Vec2::ZERO)?;
+  for _ in 0..20 {
+    let middle = (before + after) / 2.0;
+    if shape.translated(movement * middle).intersects(part) {
+      after = middle;
+    } else {
+      before = middle;
+    }
+  }
+  let normal = shape
+    .translated(movement * after)
+    .manifold(part)
+    .map_or(Vec2::ZERO, |manifold| manifold.normal);
+  Some((after, normal))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::{update_spatial_index, CircleCollider, StaticQuadTree};
+  use bevy::ecs::system::RunSystemOnce;
+
+  #[derive(Component)]
+  struct Wall;
+
+  /// Fills a small [`SpatialIndex`] with everything spawned so far.
+  fn index_world(world: &mut World) {
+    let tree = StaticQuadTree::new(Vec2::new(200.0, 200.0), 3);
+    world.insert_resource(SpatialIndex::new(tree));
+    world.run_system_once(update_spatial_index).unwrap();
+  }
+
+  fn test_world(with_index: bool) -> (World, Entity, Entity) {
+    let mut world = World::new();
+    let near = world
+      .spawn((
+        Wall,
+        Transform::from_xyz(0.0, -50.0, 0.0),
+        AxisAlignedBoundingBox::new(40.0, 10.0),
+      ))
+      .id();
+    let far = world
+      .spawn((
+        Wall,
+        Transform::from_xyz(0.0, -80.0, 0.0),
+        AxisAlignedBoundingBox::new(40.0, 10.0),
+      ))
+      .id();
+    world.spawn((
+      Transform::from_xyz(0.0, -20.0, 0.0),
+      AxisAlignedBoundingBox::new(10.0, 10.0),
+    ));
+    if with_index {
+      index_world(&mut world);
+    }
+    (world, near, far)
+  }
+
+  #[test]
+  fn test_ray_hits_nearest_matching_collider() {
+    for with_index in [false, true] {
+      let (mut world, near, _) = test_world(with_index);
+      let hit = world
+        .run_system_once(|query: SpatialQuery<With<Wall>>| {
+          query.cast_ray(Vec2::ZERO, Vec2::NEG_Y, 500.0)
+        })
+        .unwrap()
+        .unwrap();
+      assert_eq!(hit.entity, near);
+      assert_eq!(hit.distance, 45.0);
+      assert_eq!(hit.point, Vec2::new(0.0, -45.0));
+      assert_eq!(hit.normal, Vec2::Y);
+    }
+  }
+
+  #[test]
+  fn test_ray_all_is_sorted_and_limited() {
+    let (mut world, near, far) = test_world(true);
+    let hits = world
+      .run_system_once(|query: SpatialQuery<With<Wall>>| {
+        query.cast_ray_all(Vec2::ZERO, Vec2::NEG_Y, 500.0)
+      })
+      .unwrap();
+    let entities: Vec<Entity> = hits.iter().map(|hit| hit.entity).collect();
+    assert_eq!(entities, vec![near, far]);
+
+    let hits = world
+      .run_system_once(|query: SpatialQuery<With<Wall>>| {
+        query.cast_ray_all(Vec2::ZERO, Vec2::NEG_Y, 10.0)
+      })
+      .unwrap();
+    assert!(hits.is_empty());
+  }
+
+  #[test]
+  fn test_shape_cast_hits_earlier_than_ray() {
+    let (mut world, near, _) = test_world(true);
+    let hit = world
+      .run_system_once(|query: SpatialQuery<With<Wall>>| {
+        let shape = AxisAlignedBoundingBox::new(10.0, 10.0);
+        // Offset sideways so a ray would miss, but the box still clips
+        query.cast_aabb(&shape, Vec2::new(24.0, 0.0), Vec2::NEG_Y, 500.0)
+      })
+      .unwrap()
+      .unwrap();
+    assert_eq!(hit.entity, near);
+    assert_eq!(hit.distance, 40.0);
+  }
+
+  #[test]
+  fn test_point_query() {
+    let (mut world, near, _) = test_world(true);
+    let found = world
+      .run_system_once(|query: SpatialQuery| query.point(Vec2::new(5.0, -52.0)))
+      .unwrap();
+    assert_eq!(found, vec![near]);
+  }
+
+  #[test]
+  fn test_fallback_tree_is_rebuilt_each_run() {
+    let (mut world, near, far) = test_world(false);
+    // Far outside the fallback tree's screen-sized bounds
+    let distant = world
+      .spawn((
+        Wall,
+        Transform::from_xyz(0.0, -5000.0, 0.0),
+        AxisAlignedBoundingBox::new(40.0, 10.0),
+      ))
+      .id();
+    let system = world.register_system(|query: SpatialQuery<With<Wall>>| {
+      (
+        query.point(Vec2::new(0.0, -50.0)),
+        query.cast_ray_all(Vec2::ZERO, Vec2::NEG_Y, 6000.0),
+      )
+    });
+    let (found, hits) = world.run_system(system).unwrap();
+    assert_eq!(found, vec![near]);
+    let entities: Vec<Entity> = hits.iter().map(|hit| hit.entity).collect();
+    assert_eq!(entities, vec![near, far, distant]);
+
+    // The next run sees where the walls are now
+    world.entity_mut(near).insert(Transform::from_xyz(0.0, -500.0, 0.0));
+    let (found, hits) = world.run_system(system).unwrap();
+    assert!(found.is_empty());
+    let entities: Vec<Entity> = hits.iter().map(|hit| hit.entity).collect();
+    assert_eq!(entities, vec![far, near, distant]);
+  }
+
+  #[test]
+  fn test_round_colliders_are_hit_exactly() {
+    for with_index in [false, true] {
+      let mut world = World::new();
+      let ball = world
+        .spawn((
+          Wall,
+          Transform::from_xyz(0.0, -50.0, 0.0),
+          CircleCollider::new(10.0),
+        ))
+        .id();
+      if with_index {
+        index_world(&mut world);
+      }
+      let hit = world
+        .run_system_once(|query: SpatialQuery<With<Wall>>| {
+          query.cast_ray(Vec2::ZERO, Vec2::NEG_Y, 500.0)
+        })
+        .unwrap()
+        .unwrap();
+      assert_eq!(hit.entity, ball);
+      assert!((hit.distance - 40.0).abs() < 0.01);
+      assert!(hit.normal.distance(Vec2::Y) < 0.01);
+
+      // Inside the circle's box, but outside the circle
+      let (corner, middle) = world
+        .run_system_once(|query: SpatialQuery| {
+          (
+            query.point(Vec2::new(9.0, -41.0)),
+            query.point(Vec2::new(0.0, -45.0)),
+          )
+        })
+        .unwrap();
+      assert!(corner.is_empty());
+      assert_eq!(middle, vec![ball]);
+    }
+  }
+}
@@ -1,12 +1,12 @@
 use bevy::{prelude::*, utils::HashSet};
 use super::rect2d::Rect2D;
 
-#[derive(Debug, Resource)]
+#[derive(Debug, Clone, Resource)]
 pub struct StaticQuadTree {
   nodes: Vec<StaticQuadTreeNode>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StaticQuadTreeNode {
   bounds: Rect2D,
   children: Option<[usize; 4]>,
@@ -33,9 +33,18 @@ impl StaticQuadTree {
     Self { nodes }
   }
 
+  /// The deepest node `target` can be filed under so that any area
+  /// touching `target` also touches the node (or is the root).
   pub fn smallest_node(&self, target: &Rect2D) -> usize {
     let mut current_index = 0;
 
+    // Anything poking out of the tree stays in the root; filed deeper,
+    // an area touching only the part outside would miss it
+    let root = &self.nodes[0].bounds;
+    if !(root.contains(target.min()) && root.contains(target.max())) {
+      return current_index;
+    }
+
     #[allow(clippy::while_let_loop)]
     loop {
       if let Some(children) = self.nodes[current_index].children {
@@ -113,3 +122,67 @@ impl StaticQuadTree {
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::bevy_framework::bevy_collision::rect2d::test::any_rect;
+  use proptest::prelude::*;
+
+  prop_compose! {
+    /// Trees of any shape, not just square ones.
+    fn any_tree()(
+      width in 50.0_f32..3000.0,
+      height in 50.0_f32..3000.0,
+      depth in 1_usize..5,
+    ) -> StaticQuadTree {
+      StaticQuadTree::new(Vec2::new(width, height), depth)
+    }
+  }
+
+  #[test]
+  fn test_wide_tree_children_stay_inside() {
+    let tree = StaticQuadTree::new(Vec2::new(1024.0, 256.0), 2);
+    let lower_left = tree.smallest_node(&Rect2D::new(
+      Vec2::new(-500.0, -120.0),
+      Vec2::new(-490.0, -110.0),
+    ));
+    let bounds = tree.nodes[lower_left].bounds;
+    assert_eq!(bounds.min(), Vec2::new(-512.0, -128.0));
+    assert_eq!(bounds.max(), Vec2::new(-256.0, -64.0));
+  }
+
+  proptest! {
+    #[test]
+    fn children_tile_their_parent(tree in any_tree()) {
+      for node in &tree.nodes {
+        if let Some(children) = node.children {
+          let quadrants: Vec<Rect2D> =
+            children.iter().map(|child| tree.nodes[*child].bounds).collect();
+          prop_assert_eq!(quadrants, node.bounds.quadrants());
+        }
+      }
+    }
+
+    #[test]
+    fn touching_areas_reach_the_node(
+      tree in any_tree(),
+      target in any_rect(),
+      along in (0.0_f32..=1.0, 0.0_f32..=1.0),
+      below in (0.0_f32..500.0, 0.0_f32..500.0),
+      above in (0.0_f32..500.0, 0.0_f32..500.0),
+    ) {
+      // An area around some point of the target, so the two touch
+      let point = target.min() + target.size() * Vec2::new(along.0, along.1);
+      let area = Rect2D::new(
+        point - Vec2::new(below.0, below.1),
+        point + Vec2::new(above.0, above.1),
+      );
+      let node = tree.smallest_node(&target);
+      prop_assert!(tree.nodes[node].bounds.intersect(&target) || node == 0);
+      let mut reached = tree.intersecting_nodes(&area);
+      reached.insert(0);
+      prop_assert!(reached.contains(&node));
+    }
+  }
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use super::{rect2d::Rect2D, world_transform};
+
+/// Opts an entity into continuous collision detection. Instead of only
+/// testing where its box ends up, collision checks sweep the box from
+/// where it was when the physics step started, so fast movers can't
+/// tunnel through thin obstacles.
+#[derive(Component, Default)]
+pub struct ContinuousCollision {
+  pub(crate) previous: Option<Vec2>,
+}
+
+/// Remembers where in the world each continuous-collision entity
+/// starts the physics step. Runs after the physics clock, before
+/// anything moves.
+pub fn record_sweep_start(
+  mut query: Query<(
+    &Transform,
+    Option<&GlobalTransform>,
+    Has<Parent>,
+    &mut ContinuousCollision,
+  )>,
+) {
+  query
+    .iter_mut()
+    .for_each(|(transform, global, has_parent, mut ccd)| {
+      let start = world_transform(transform, global, has_parent);
+      ccd.previous = Some(start.translation.truncate());
+    });
+}
+
+/// Sweeps `a` by `a_move` and `b` by `b_move` (both starting at the
+/// given rectangles) and returns the time of impact, from 0 (touching
+/// at the start) to 1 (touching at the end), or `None` if they never
+/// touch.
+pub fn sweep(
+  a: &Rect2D,
+  a_move: Vec2,
+  b: &Rect2D,
+  b_move: Vec2,
+) -> Option<f32> {
+  sweep_with_normal(a, a_move, b, b_move).map(|(time, _)| time)
+}
+
+/// Like [`sweep`], but also returns the normal of the face of `b` that
+/// `a` hits first. The normal is zero if they already overlap.
+pub fn sweep_with_normal(
+  a: &Rect2D,
+  a_move: Vec2,
+  b: &Rect2D,
+  b_move: Vec2,
+) -> Option<(f32, Vec2)> {
+  // Treat `b` as still and `a` as a point moving through `b` grown by
+  // `a`'s half size.
+  let movement = a_move - b_move;
+  let half = (a.max() - a.min()) / 2.0;
+  let start = a.center();
+  let min = b.min() - half;
+  let max = b.max() + half;
+
+  let mut enter = f32::NEG_INFINITY;
+  let mut exit = f32::INFINITY;
+  let mut normal = Vec2::ZERO;
+  for axis in 0..2 {
+    if movement[axis] == 0.0 {
+      if start[axis] < min[axis] || start[axis] > max[axis] {
+        return None;
+      }
+    } else {
+      let t1 = (min[axis] - start[axis]) / movement[axis];
+      let t2 = (max[axis] - start[axis]) / movement[axis];
+      let axis_enter = t1.min(t2);
+      if axis_enter > enter {
+        enter = axis_enter;
+        normal = Vec2::ZERO;
+        normal[axis] = -movement[axis].signum();
+      }
+      exit = exit.min(t1.max(t2));
+    }
+  }
+
+  if enter > exit || exit < 0.0 || enter > 1.0 {
+    None
+  } else if enter <= 0.0 {
+    Some((0.0, Vec2::ZERO))
+  } else {
+    Some((enter, normal))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn square(x: f32, y: f32, size: f32) -> Rect2D {
+    let half = size / 2.0;
+    Rect2D::new(Vec2::new(x - half, y - half), Vec2::new(x + half, y + half))
+  }
+
+  #[test]
+  fn test_fast_box_hits_thin_wall() {
+    let bullet = square(0.0, 0.0, 4.0);
+    let wall = Rect2D::new(Vec2::new(50.0, -20.0), Vec2::new(52.0, 20.0));
+    let toi = sweep(&bullet, Vec2::new(100.0, 0.0), &wall, Vec2::ZERO);
+    assert_eq!(toi, Some(0.48));
+  }
+
+  #[test]
+  fn test_miss_returns_none() {
+    let bullet = square(0.0, 0.0, 4.0);
+    let wall = Rect2D::new(Vec2::new(50.0, 10.0), Vec2::new(52.0, 20.0));
+    assert_eq!(sweep(&bullet, Vec2::new(100.0, 0.0), &wall, Vec2::ZERO), None);
+    // Stops short of the wall
+    let wall = Rect2D::new(Vec2::new(150.0, -20.0), Vec2::new(152.0, 20.0));
+    assert_eq!(sweep(&bullet, Vec2::new(100.0, 0.0), &wall, Vec2::ZERO), None);
+  }
+
+  #[test]
+  fn test_already_overlapping_is_time_zero() {
+    let a = square(0.0, 0.0, 4.0);
+    let b = square(1.0, 1.0, 4.0);
+    assert_eq!(sweep(&a, Vec2::new(-10.0, 0.0), &b, Vec2::ZERO), Some(0.0));
+    assert_eq!(sweep(&a, Vec2::ZERO, &b, Vec2::ZERO), Some(0.0));
+  }
+
+  #[test]
+  fn test_both_moving_uses_relative_motion() {
+    let a = square(0.0, 0.0, 2.0);
+    let b = square(10.0, 0.0, 2.0);
+    let toi = sweep(&a, Vec2::new(4.0, 0.0), &b, Vec2::new(-4.0, 0.0));
+    assert_eq!(toi, Some(1.0));
+  }
+}
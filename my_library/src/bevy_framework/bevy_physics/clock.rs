@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Controls how often the physics simulation steps.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PhysicsConfig {
+    /// Physics ticks per second
+    pub tick_rate: f32,
+    /// The most ticks a single frame may emit while catching up. Any
+    /// time beyond that is discarded, so a long stall (a breakpoint,
+    /// a dragged window) doesn't cause a burst of simulation.
+    pub max_catch_up_ticks: u32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            tick_rate: 30.0,
+            max_catch_up_ticks: 5,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    /// Panics unless `tick_rate` is positive and finite.
+    pub fn new(tick_rate: f32, max_catch_up_ticks: u32) -> Self {
+        let config = Self {
+            tick_rate,
+            max_catch_up_ticks,
+        };
+        config.assert_valid();
+        config
+    }
+
+    /// Panics unless `tick_rate` is positive and finite; anything else
+    /// has no tick duration.
+    pub fn assert_valid(&self) {
+        assert!(
+            self.tick_rate.is_finite() && self.tick_rate > 0.0,
+            "The physics tick rate must be positive, not {}",
+            self.tick_rate
+        );
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.tick_rate)
+    }
+}
+
+/// Accumulates frame time and hands it out as whole physics ticks.
+#[derive(Resource, Default, Debug)]
+pub struct PhysicsTimer {
+    accumulated: Duration,
+    overstep: f32,
+    frame: Duration,
+}
+
+impl PhysicsTimer {
+    /// Adds `delta` to the accumulator and returns how many ticks
+    /// should run. The remainder is kept for the next frame.
+    pub fn advance(&mut self, delta: Duration, config: &PhysicsConfig) -> u32 {
+        let tick = config.tick_duration();
+        self.frame = delta;
+        self.accumulated += delta;
+        let mut ticks = 0;
+        while self.accumulated >= tick {
+            self.accumulated -= tick;
+            ticks += 1;
+        }
+        if ticks > config.max_catch_up_ticks {
+            ticks = config.max_catch_up_ticks;
+        }
+        self.overstep = self.accumulated.as_secs_f32() / tick.as_secs_f32();
+        ticks
+    }
+
+    /// How far (0..1) we are between the last tick and the next one.
+    pub fn overstep_fraction(&self) -> f32 {
+        self.overstep
+    }
+
+    /// How much physics time the current frame covers: the scaled frame
+    /// time, or the stepped ticks while paused.
+    pub fn frame_duration(&self) -> Duration {
+        self.frame
+    }
+}
+
+/// Controls how fast physics time passes relative to real time. Only
+/// physics is affected; UI and animations keep running.
+#[derive(Resource, Debug)]
+pub struct PhysicsTime {
+    scale: f32,
+    paused: bool,
+    pending_steps: u32,
+}
+
+impl Default for PhysicsTime {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+        }
+    }
+}
+
+impl PhysicsTime {
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// 1.0 is normal speed, 0.5 is half speed (slow motion) and so on.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// While paused, runs exactly one physics tick on the next frame.
+    /// Handy for stepping through collision bugs.
+    pub fn step(&mut self) {
+        self.pending_steps += 1;
+    }
+}
+
+#[derive(Event)]
+pub struct PhysicsTick;
+
+pub fn physics_clock(
+    mut clock: ResMut<PhysicsTimer>,
+    mut physics_time: ResMut<PhysicsTime>,
+    config: Res<PhysicsConfig>,
+    time: Res<Time>,
+    mut on_tick: EventWriter<PhysicsTick>,
+) {
+    let ticks = if physics_time.paused {
+        let steps = std::mem::take(&mut physics_time.pending_steps);
+        clock.frame = config.tick_duration() * steps;
+        steps
+    } else {
+        physics_time.pending_steps = 0;
+        clock.advance(time.delta().mul_f32(physics_time.scale), &config)
+    };
+    for _ in 0..ticks {
+        on_tick.send(PhysicsTick);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    /// Runs the clock over a frame `frame_ticks` ticks long, returning
+    /// how many ticks it emitted.
+    fn run_clock(world: &mut World, frame_ticks: u32) -> usize {
+        let tick = world.resource::<PhysicsConfig>().tick_duration();
+        world.resource_mut::<Time>().advance_by(tick * frame_ticks);
+        world.run_system_once(physics_clock).unwrap();
+        world.resource_mut::<Events<PhysicsTick>>().drain().count()
+    }
+
+    fn clock_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Events<PhysicsTick>>();
+        world.init_resource::<Time>();
+        world.insert_resource(PhysicsConfig::new(30.0, 10));
+        world.init_resource::<PhysicsTimer>();
+        world.init_resource::<PhysicsTime>();
+        world
+    }
+
+    #[test]
+    fn test_time_scale_slows_physics() {
+        let mut world = clock_world();
+        world.resource_mut::<PhysicsTime>().set_scale(0.5);
+        assert_eq!(run_clock(&mut world, 4), 2);
+    }
+
+    #[test]
+    fn test_pause_and_step() {
+        let mut world = clock_world();
+        world.resource_mut::<PhysicsTime>().pause();
+        assert_eq!(run_clock(&mut world, 4), 0);
+        world.resource_mut::<PhysicsTime>().step();
+        assert_eq!(run_clock(&mut world, 4), 1);
+        // Only the stepped tick counts as physics time
+        let tick = world.resource::<PhysicsConfig>().tick_duration();
+        assert_eq!(world.resource::<PhysicsTimer>().frame_duration(), tick);
+        assert_eq!(run_clock(&mut world, 4), 0);
+        let timer = world.resource::<PhysicsTimer>();
+        assert_eq!(timer.frame_duration(), Duration::ZERO);
+        world.resource_mut::<PhysicsTime>().resume();
+        assert_eq!(run_clock(&mut world, 4), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "tick rate must be positive")]
+    fn test_zero_tick_rate_is_rejected() {
+        PhysicsConfig::new(0.0, 5);
+    }
+
+    #[test]
+    fn test_long_frame_catches_up() {
+        let config = PhysicsConfig::new(30.0, 5);
+        let mut timer = PhysicsTimer::default();
+        let ticks = timer.advance(config.tick_duration() * 3, &config);
+        assert_eq!(ticks, 3);
+    }
+
+    #[test]
+    fn test_remainder_is_kept() {
+        let config = PhysicsConfig::new(30.0, 5);
+        let mut timer = PhysicsTimer::default();
+        let half = config.tick_duration() / 2;
+        assert_eq!(timer.advance(half, &config), 0);
+        assert!((timer.overstep_fraction() - 0.5).abs() < 0.01);
+        let rest = config.tick_duration() - half;
+        assert_eq!(timer.advance(rest, &config), 1);
+    }
+
+    #[test]
+    fn test_catch_up_is_capped() {
+        let config = PhysicsConfig::new(30.0, 5);
+        let mut timer = PhysicsTimer::default();
+        let ticks = timer.advance(Duration::from_secs(10), &config);
+        assert_eq!(ticks, 5);
+        assert!(timer.overstep_fraction() < 1.0);
+    }
+}
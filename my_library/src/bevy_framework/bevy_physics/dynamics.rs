@@ -0,0 +1,469 @@
+use super::{
+    PhysicsConfig, PhysicsIntegrator, PhysicsInterpolation, PhysicsPose,
+    PhysicsTick, PhysicsTimer, RigidBody, RigidBodyQuery, resolve_contacts,
+};
+use crate::{CollisionBroadphase, Sensor};
+use bevy::{ecs::query::QueryData, prelude::*};
+
+/// Movement in world units per second.
+#[derive(Component)]
+pub struct Velocity(pub Vec3);
+impl Default for Velocity {
+    fn default() -> Self {
+        Self(Vec3::ZERO)
+    }
+}
+impl Velocity {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self(Vec3 { x, y, z })
+    }
+}
+
+/// How heavy an entity is. A [`Force`] is divided by the mass before it
+/// changes velocity; entities without a `Mass` behave as if it were 1.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Mass(f32);
+impl Default for Mass {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+impl Mass {
+    pub fn new(mass: f32) -> Self {
+        assert!(mass > 0.0, "Mass must be positive");
+        Self(mass)
+    }
+
+    pub fn get(&self) -> f32 {
+        self.0
+    }
+
+    pub fn inverse(&self) -> f32 {
+        1.0 / self.0
+    }
+}
+
+/// A constant acceleration (units per second, per second) that is
+/// applied every tick until it is changed or removed. Useful for thrust.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Acceleration(pub Vec3);
+
+/// Accumulates the forces pushing an entity this frame. Game systems
+/// may add to it any number of times before [`PhysicsSet::Integrate`];
+/// the total acts for as much physics time as the frame covers (see
+/// [`PhysicsTimer::frame_duration`]) and is then cleared. The push is
+/// delivered, scaled by [`Mass`], on the next tick, so thrust added
+/// every frame pushes equally hard whatever the frame rate.
+///
+/// [`PhysicsSet::Integrate`]: super::PhysicsSet::Integrate
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Force {
+    current: Vec3,
+    /// Force times physics time from frames since the last tick
+    impulse: Vec3,
+}
+impl Force {
+    pub fn new(force: Vec3) -> Self {
+        Self {
+            current: force,
+            impulse: Vec3::ZERO,
+        }
+    }
+
+    pub fn add(&mut self, force: Vec3) {
+        self.current += force;
+    }
+
+    /// The force added so far this frame.
+    pub fn get(&self) -> Vec3 {
+        self.current
+    }
+
+    pub fn clear(&mut self) {
+        self.current = Vec3::ZERO;
+    }
+}
+
+/// Spin around the Z axis, in radians per second.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AngularVelocity(pub f32);
+
+/// Slows an entity's [`Velocity`], like air resistance. Each tick
+/// divides the speed by `1 + damping * dt`, so it decays roughly like
+/// `e^(-damping * t)`: 1.0 leaves about 37% of the speed after a
+/// second, 2.0 about 14%. 0 disables it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct LinearDamping(pub f32);
+
+/// Slows an entity's [`AngularVelocity`], decaying spin the same way
+/// [`LinearDamping`] decays speed. 0 disables it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct AngularDamping(pub f32);
+
+/// The acceleration (units per second, per second) applied to every
+/// entity with [`ApplyGravity`]. Use `Vec3::ZERO` for zero-g, or point
+/// it sideways.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Gravity(pub Vec3);
+impl Default for Gravity {
+    fn default() -> Self {
+        Self(Vec3::new(0.0, -675.0, 0.0))
+    }
+}
+
+#[derive(Component)]
+pub struct ApplyGravity;
+
+/// Multiplies the global [`Gravity`] for one entity. Entities with
+/// [`ApplyGravity`] but no scale use 1.0.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct GravityScale(pub f32);
+
+/// The fastest an entity may fall, measured along the direction of
+/// [`Gravity`]. Movement across or against gravity isn't limited, and
+/// with zero gravity there is no "down", so nothing is limited.
+///
+/// The limit holds during the tick too: gravity and forces are held
+/// back so the entity never moves faster than this.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TerminalVelocity(pub f32);
+
+/// Everything the integrator reads or writes for one entity.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct Body {
+    transform: &'static mut Transform,
+    velocity: Option<&'static mut Velocity>,
+    angular: Option<&'static mut AngularVelocity>,
+    mass: Option<&'static Mass>,
+    acceleration: Option<&'static Acceleration>,
+    force: Option<&'static mut Force>,
+    gravity: Has<ApplyGravity>,
+    gravity_scale: Option<&'static GravityScale>,
+    terminal: Option<&'static TerminalVelocity>,
+    linear_damping: Option<&'static LinearDamping>,
+    angular_damping: Option<&'static AngularDamping>,
+    interpolation: Option<&'static mut PhysicsInterpolation>,
+}
+
+/// Divides `value` by `1 + damping * dt`, an implicit step of
+/// exponential decay that can't overshoot zero however long the tick.
+fn damp(value: f32, damping: f32, dt: f32) -> f32 {
+    value / (1.0 + damping.max(0.0) * dt)
+}
+
+/// Banks this frame's [`Force`]s, then runs each of this frame's ticks
+/// for every moving entity: gravity, forces and acceleration change
+/// velocity, the chosen
+/// [`PhysicsIntegrator`] moves the entity, and damping and terminal
+/// velocity are applied. If there is a `CollisionBroadphase<RigidBody>`
+/// (the [`PhysicsPlugin`](super::PhysicsPlugin) adds one), overlapping
+/// [`RigidBody`]s are then pushed apart before the next tick, so
+/// catching up never piles up penetration.
+#[allow(clippy::type_complexity)]
+pub fn apply_velocity(
+    mut tick: EventReader<PhysicsTick>,
+    config: Res<PhysicsConfig>,
+    integrator: Res<PhysicsIntegrator>,
+    gravity: Res<Gravity>,
+    timer: Res<PhysicsTimer>,
+    mut broadphase: Option<ResMut<CollisionBroadphase<RigidBody>>>,
+    mut queries: ParamSet<(
+        Query<Body, Or<(With<Velocity>, With<AngularVelocity>)>>,
+        Query<RigidBodyQuery, Without<Sensor>>,
+        Query<&mut Force>,
+    )>,
+) {
+    let dt = config.tick_duration().as_secs_f32();
+    let down = gravity.0.normalize_or_zero();
+    let frame = timer.frame_duration().as_secs_f32();
+    queries.p2().iter_mut().for_each(|mut force| {
+        let current = std::mem::take(&mut force.current);
+        force.impulse += current * frame;
+    });
+    for _tick in tick.read() {
+        queries.p0().iter_mut().for_each(|mut body| {
+            if let Some(interpolation) = &mut body.interpolation {
+                interpolation.previous = PhysicsPose::of(&body.transform);
+            }
+
+            if let Some(velocity) = &mut body.velocity {
+                let mut acceleration =
+                    body.acceleration.map_or(Vec3::ZERO, |a| a.0);
+                if body.gravity {
+                    let scale = body.gravity_scale.map_or(1.0, |s| s.0);
+                    acceleration += gravity.0 * scale;
+                }
+                if let Some(force) = &mut body.force {
+                    // Delivered over this tick, however many frames
+                    // it built up over
+                    let impulse = std::mem::take(&mut force.impulse);
+                    acceleration +=
+                        impulse / dt * body.mass.map_or(1.0, Mass::inverse);
+                }
+                if let Some(terminal) = body.terminal {
+                    // Pull no harder than it takes to reach terminal
+                    // velocity this tick, so the move itself is capped
+                    let limit = (terminal.0 - velocity.0.dot(down)) / dt;
+                    let pull = acceleration.dot(down);
+                    if pull > limit {
+                        acceleration -= down * (pull - limit);
+                    }
+                }
+
+                let (position, mut new_velocity) = integrator.step(
+                    body.transform.translation,
+                    velocity.0,
+                    dt,
+                    |_| acceleration,
+                );
+                if let Some(damping) = body.linear_damping {
+                    new_velocity *= damp(1.0, damping.0, dt);
+                }
+                body.transform.translation = position;
+                velocity.0 = new_velocity;
+            }
+
+            if let Some(angular) = &mut body.angular {
+                body.transform.rotate_z(angular.0 * dt);
+                if let Some(damping) = body.angular_damping {
+                    angular.0 = damp(angular.0, damping.0, dt);
+                }
+            }
+        });
+        if let Some(broadphase) = &mut broadphase {
+            resolve_contacts(&mut queries.p1(), broadphase.backend_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AxisAlignedBoundingBox, QuadTreeBroadphase};
+    use bevy::ecs::system::RunSystemOnce;
+    use std::time::Duration;
+
+    /// A world ticking once per second, so dt is 1 and the expected
+    /// numbers stay simple.
+    fn test_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Events<PhysicsTick>>();
+        world.insert_resource(PhysicsConfig::new(1.0, 5));
+        world.init_resource::<PhysicsTimer>();
+        world.init_resource::<PhysicsIntegrator>();
+        world.init_resource::<Gravity>();
+        world
+    }
+
+    /// Runs a frame covering `frame` of physics time, with however many
+    /// ticks that adds up to.
+    fn run_frame(world: &mut World, frame: Duration) {
+        let config = *world.resource::<PhysicsConfig>();
+        let ticks =
+            world.resource_mut::<PhysicsTimer>().advance(frame, &config);
+        world.send_event_batch((0..ticks).map(|_| PhysicsTick));
+        world.run_system_once(apply_velocity).unwrap();
+    }
+
+    fn run_ticks(world: &mut World, ticks: u32) {
+        let tick = world.resource::<PhysicsConfig>().tick_duration();
+        run_frame(world, tick * ticks);
+    }
+
+    #[test]
+    fn test_force_is_scaled_by_mass_and_cleared() {
+        let mut world = test_world();
+        let light = world
+            .spawn((
+                Transform::default(),
+                Velocity::default(),
+                Force::new(Vec3::new(4.0, 0.0, 0.0)),
+            ))
+            .id();
+        let heavy = world
+            .spawn((
+                Transform::default(),
+                Velocity::default(),
+                Mass::new(2.0),
+                Force::new(Vec3::new(4.0, 0.0, 0.0)),
+            ))
+            .id();
+        // One frame two seconds long, so the force acts for two seconds
+        run_ticks(&mut world, 2);
+
+        assert_eq!(world.get::<Velocity>(light).unwrap().0.x, 8.0);
+        assert_eq!(world.get::<Velocity>(heavy).unwrap().0.x, 4.0);
+        assert_eq!(world.get::<Force>(heavy).unwrap().get(), Vec3::ZERO);
+        run_ticks(&mut world, 1);
+        assert_eq!(world.get::<Velocity>(heavy).unwrap().0.x, 4.0);
+    }
+
+    #[test]
+    fn test_force_added_every_frame_ignores_frame_rate() {
+        for frames_per_tick in [1, 4, 8] {
+            let mut world = test_world();
+            let entity = world
+                .spawn((
+                    Transform::default(),
+                    Velocity::default(),
+                    Force::default(),
+                ))
+                .id();
+            let frame = Duration::from_secs(1) / frames_per_tick;
+            for frame_index in 1..=frames_per_tick {
+                world
+                    .get_mut::<Force>(entity)
+                    .unwrap()
+                    .add(Vec3::new(6.0, 0.0, 0.0));
+                run_frame(&mut world, frame);
+                // Nothing moves until the tick at the end of the second
+                let expected = if frame_index == frames_per_tick {
+                    6.0
+                } else {
+                    0.0
+                };
+                let velocity = world.get::<Velocity>(entity).unwrap().0;
+                assert_eq!(velocity.x, expected, "{frames_per_tick} frames");
+            }
+        }
+    }
+
+    #[test]
+    fn test_acceleration_applies_every_tick() {
+        let mut world = test_world();
+        let entity = world
+            .spawn((
+                Transform::default(),
+                Velocity::default(),
+                Acceleration(Vec3::Y),
+                Mass::new(5.0),
+            ))
+            .id();
+        run_ticks(&mut world, 3);
+
+        assert_eq!(world.get::<Velocity>(entity).unwrap().0, Vec3::Y * 3.0);
+    }
+
+    #[test]
+    fn test_gravity_scale_and_terminal_velocity() {
+        let mut world = test_world();
+        world.insert_resource(Gravity(Vec3::new(-1.0, 0.0, 0.0)));
+        let normal = world
+            .spawn((Transform::default(), Velocity::default(), ApplyGravity))
+            .id();
+        let floaty = world
+            .spawn((
+                Transform::default(),
+                Velocity::default(),
+                ApplyGravity,
+                GravityScale(0.5),
+            ))
+            .id();
+        let capped = world
+            .spawn((
+                Transform::default(),
+                Velocity::new(0.0, 7.0, 0.0),
+                ApplyGravity,
+                TerminalVelocity(2.5),
+            ))
+            .id();
+        run_ticks(&mut world, 3);
+
+        let velocity = |entity| world.get::<Velocity>(entity).unwrap().0;
+        assert_eq!(velocity(normal), Vec3::new(-3.0, 0.0, 0.0));
+        assert_eq!(velocity(floaty), Vec3::new(-1.5, 0.0, 0.0));
+        // Only the component along gravity is limited
+        assert_eq!(velocity(capped), Vec3::new(-2.5, 7.0, 0.0));
+    }
+
+    #[test]
+    fn test_terminal_velocity_caps_the_move() {
+        let mut world = test_world();
+        world.insert_resource(Gravity(Vec3::new(0.0, -10.0, 0.0)));
+        let entity = world
+            .spawn((
+                Transform::default(),
+                Velocity::new(0.0, -2.0, 0.0),
+                ApplyGravity,
+                TerminalVelocity(3.0),
+            ))
+            .id();
+        run_ticks(&mut world, 1);
+
+        assert_eq!(world.get::<Velocity>(entity).unwrap().0.y, -3.0);
+        let translation = world.get::<Transform>(entity).unwrap().translation;
+        assert_eq!(translation.y, -3.0);
+    }
+
+    #[test]
+    fn test_contacts_resolved_every_tick() {
+        let mut world = test_world();
+        world.insert_resource(CollisionBroadphase::<RigidBody>::new(
+            QuadTreeBroadphase::default(),
+        ));
+        world.spawn((
+            RigidBody::Static,
+            AxisAlignedBoundingBox::new(10.0, 10.0),
+            Transform::default(),
+        ));
+        let falling = world
+            .spawn((
+                RigidBody::Dynamic,
+                AxisAlignedBoundingBox::new(10.0, 10.0),
+                Transform::from_xyz(0.0, 10.0, 0.0),
+                Velocity::new(0.0, -6.0, 0.0),
+            ))
+            .id();
+        // Resolved only after all three ticks, it would end up below
+        run_ticks(&mut world, 3);
+
+        let translation = world.get::<Transform>(falling).unwrap().translation;
+        assert_eq!(translation.y, 10.0);
+        assert_eq!(world.get::<Velocity>(falling).unwrap().0, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_damping_slows_entities() {
+        let mut world = test_world();
+        let entity = world
+            .spawn((
+                Transform::default(),
+                Velocity::new(8.0, 0.0, 0.0),
+                LinearDamping(1.0),
+                AngularVelocity(4.0),
+                AngularDamping(1.0),
+            ))
+            .id();
+        run_ticks(&mut world, 2);
+
+        assert_eq!(world.get::<Velocity>(entity).unwrap().0.x, 2.0);
+        assert_eq!(world.get::<AngularVelocity>(entity).unwrap().0, 1.0);
+    }
+
+    #[test]
+    fn test_angular_velocity_rotates_transform() {
+        let mut world = test_world();
+        let entity = world
+            .spawn((Transform::default(), AngularVelocity(0.25)))
+            .id();
+        run_ticks(&mut world, 2);
+
+        let rotation = world.get::<Transform>(entity).unwrap().rotation;
+        let (angle, _, _) = rotation.to_euler(EulerRot::ZYX);
+        assert!((angle - 0.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_velocity_is_scaled_by_tick_length() {
+        let mut world = test_world();
+        world.insert_resource(PhysicsConfig::new(4.0, 5));
+        let entity = world
+            .spawn((Transform::default(), Velocity::new(8.0, 0.0, 0.0)))
+            .id();
+        run_ticks(&mut world, 2);
+
+        let translation = world.get::<Transform>(entity).unwrap().translation;
+        assert_eq!(translation, Vec3::new(4.0, 0.0, 0.0));
+    }
+}
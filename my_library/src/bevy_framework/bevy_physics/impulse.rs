@@ -0,0 +1,141 @@
+use super::Velocity;
+use bevy::prelude::*;
+
+/// A change to an entity's velocity. Impulses are applied in the
+/// order they were sent, except that a higher `priority` is always
+/// applied after a lower one. An `absolute` impulse replaces the
+/// target's velocity, discarding impulses applied to that target before
+/// it; impulses that come after it still add on top.
+#[derive(Event, Debug, Clone)]
+pub struct Impulse {
+    pub target: Entity,
+    pub amount: Vec3,
+    pub absolute: bool,
+    pub priority: i32,
+    /// Optional label for the system that sent the impulse, logged
+    /// at `trace` level to help track down who pushed what.
+    pub source: Option<&'static str>,
+}
+
+impl Impulse {
+    /// An impulse that adds `amount` to the target's velocity.
+    pub fn add(target: Entity, amount: Vec3) -> Self {
+        Self {
+            target,
+            amount,
+            absolute: false,
+            priority: 0,
+            source: None,
+        }
+    }
+
+    /// An impulse that sets the target's velocity to `amount`.
+    pub fn set(target: Entity, amount: Vec3) -> Self {
+        Self {
+            absolute: true,
+            ..Self::add(target, amount)
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_source(mut self, source: &'static str) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+pub fn sum_impulses(
+    mut impulses: EventReader<Impulse>,
+    mut velocities: Query<&mut Velocity>,
+) {
+    let mut ordered: Vec<&Impulse> = impulses.read().collect();
+    // Stable, so equal priorities keep the order they were sent in
+    ordered.sort_by_key(|impulse| impulse.priority);
+    for impulse in ordered {
+        if let Some(source) = impulse.source {
+            bevy::log::trace!("{source}: {impulse:?}");
+        }
+        if let Ok(mut velocity) = velocities.get_mut(impulse.target) {
+            if impulse.absolute {
+                velocity.0 = impulse.amount;
+            } else {
+                velocity.0 += impulse.amount;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn run_impulses(impulses: Vec<Impulse>, world: &mut World) {
+        world.init_resource::<Events<Impulse>>();
+        world.send_event_batch(impulses);
+        world.run_system_once(sum_impulses).unwrap();
+    }
+
+    #[test]
+    fn test_absolute_impulse_does_not_drop_others() {
+        let mut world = World::new();
+        let a = world.spawn(Velocity::new(5.0, 0.0, 0.0)).id();
+        let b = world.spawn(Velocity::default()).id();
+        let c = world.spawn(Velocity::default()).id();
+        run_impulses(
+            vec![
+                Impulse::add(a, Vec3::Y),
+                Impulse::set(a, Vec3::X),
+                Impulse::add(b, Vec3::Y),
+                Impulse::add(a, Vec3::Y),
+                Impulse::add(c, Vec3::X),
+                Impulse::add(c, Vec3::X),
+            ],
+            &mut world,
+        );
+        assert_eq!(
+            world.get::<Velocity>(a).unwrap().0,
+            Vec3::new(1.0, 1.0, 0.0)
+        );
+        assert_eq!(world.get::<Velocity>(b).unwrap().0, Vec3::Y);
+        assert_eq!(
+            world.get::<Velocity>(c).unwrap().0,
+            Vec3::new(2.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_absolute_impulse_only_affects_its_target() {
+        let mut world = World::new();
+        let a = world.spawn(Velocity::default()).id();
+        let b = world.spawn(Velocity::new(0.0, 3.0, 0.0)).id();
+        run_impulses(
+            vec![Impulse::add(b, Vec3::Y), Impulse::set(a, Vec3::X)],
+            &mut world,
+        );
+        assert_eq!(world.get::<Velocity>(a).unwrap().0, Vec3::X);
+        assert_eq!(
+            world.get::<Velocity>(b).unwrap().0,
+            Vec3::new(0.0, 4.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_higher_priority_applies_last() {
+        let mut world = World::new();
+        let a = world.spawn(Velocity::default()).id();
+        run_impulses(
+            vec![
+                Impulse::set(a, Vec3::Z).with_priority(10),
+                Impulse::set(a, Vec3::X).with_source("test"),
+                Impulse::add(a, Vec3::Y),
+            ],
+            &mut world,
+        );
+        assert_eq!(world.get::<Velocity>(a).unwrap().0, Vec3::Z);
+    }
+}
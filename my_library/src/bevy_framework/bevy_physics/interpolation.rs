@@ -0,0 +1,76 @@
+use super::PhysicsTimer;
+use bevy::prelude::*;
+
+/// The parts of a `Transform` that physics moves.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) struct PhysicsPose {
+    translation: Vec3,
+    rotation: Quat,
+}
+
+impl PhysicsPose {
+    pub(crate) fn of(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+
+    fn apply_to(&self, transform: &mut Transform) {
+        transform.translation = self.translation;
+        transform.rotation = self.rotation;
+    }
+
+    fn lerp(&self, other: &Self, alpha: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(other.translation, alpha),
+            rotation: self.rotation.slerp(other.rotation, alpha),
+        }
+    }
+}
+
+/// Opt-in smoothing: physics moves the entity in whole ticks, and the
+/// rendered `Transform` is blended between the last two ticks.
+#[derive(Component, Default)]
+pub struct PhysicsInterpolation {
+    pub(crate) previous: PhysicsPose,
+    current: PhysicsPose,
+    rendered: PhysicsPose,
+}
+
+/// Puts interpolated entities back at their real physics position
+/// before the physics step runs. If something else moved the entity
+/// since we last rendered it, that move is treated as a teleport.
+pub fn restore_physics_transforms(
+    mut query: Query<(&mut Transform, &mut PhysicsInterpolation)>,
+) {
+    query
+        .iter_mut()
+        .for_each(|(mut transform, mut interpolation)| {
+            let pose = PhysicsPose::of(&transform);
+            if pose != interpolation.rendered {
+                interpolation.previous = pose;
+                interpolation.current = pose;
+            }
+            interpolation.current.apply_to(&mut transform);
+        });
+}
+
+/// Stores the post-physics position and blends the rendered position
+/// between the previous and current tick.
+pub fn interpolate_physics_transforms(
+    timer: Res<PhysicsTimer>,
+    mut query: Query<(&mut Transform, &mut PhysicsInterpolation)>,
+) {
+    let alpha = timer.overstep_fraction();
+    query
+        .iter_mut()
+        .for_each(|(mut transform, mut interpolation)| {
+            interpolation.current = PhysicsPose::of(&transform);
+            let rendered =
+                interpolation.previous.lerp(&interpolation.current, alpha);
+            rendered.apply_to(&mut transform);
+            // Read back, so the teleport check compares like with like
+            interpolation.rendered = PhysicsPose::of(&transform);
+        });
+}
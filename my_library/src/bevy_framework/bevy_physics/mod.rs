@@ -0,0 +1,206 @@
+use crate::{
+    Broadphase, CollisionBroadphase, QuadTreeBroadphase, follow_camera,
+};
+use bevy::prelude::*;
+
+mod clock;
+pub use clock::*;
+
+mod interpolation;
+pub use interpolation::*;
+
+mod impulse;
+pub use impulse::*;
+
+mod integrator;
+pub use integrator::*;
+
+mod dynamics;
+pub use dynamics::*;
+
+mod rigid_body;
+pub use rigid_body::*;
+
+/// The stages of a physics frame, in the order they run. Attach game
+/// systems with `.before(...)`/`.after(...)`/`.in_set(...)`; for example
+/// input that sends an [`Impulse`] should run before
+/// [`PhysicsSet::Forces`] so it is applied in the same frame.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    /// Advances the physics clock and emits [`PhysicsTick`] events
+    Clock,
+    /// Impulses adjust velocities
+    Forces,
+    /// Gravity, forces and damping are integrated, entities move and
+    /// overlapping rigid bodies are pushed apart, one tick at a time
+    Integrate,
+    /// Collision detection
+    Collide,
+    /// Blends rendered transforms between physics ticks
+    Interpolate,
+}
+
+/// Registers the physics systems, ordered by [`PhysicsSet`], and runs
+/// them only while the app is in one of the given states.
+///
+/// [`RigidBody`] contacts are found with a [`QuadTreeBroadphase`]
+/// covering a 1024x768 screen unless another [`Broadphase`] is chosen
+/// with `with_broadphase`.
+pub struct PhysicsPlugin<S: States, P = QuadTreeBroadphase> {
+    states: Vec<S>,
+    config: PhysicsConfig,
+    integrator: PhysicsIntegrator,
+    broadphase: P,
+}
+
+impl<S: States> PhysicsPlugin<S> {
+    pub fn new<I: IntoIterator<Item = S>>(states: I) -> Self {
+        Self {
+            states: states.into_iter().collect(),
+            config: PhysicsConfig::default(),
+            integrator: PhysicsIntegrator::default(),
+            broadphase: QuadTreeBroadphase::default(),
+        }
+    }
+}
+
+impl<S: States, P> PhysicsPlugin<S, P> {
+    pub fn with_broadphase<Q: Broadphase + Clone>(
+        self,
+        broadphase: Q,
+    ) -> PhysicsPlugin<S, Q> {
+        PhysicsPlugin {
+            states: self.states,
+            config: self.config,
+            integrator: self.integrator,
+            broadphase,
+        }
+    }
+
+    /// Panics if the config's tick rate isn't positive and finite.
+    pub fn with_config(mut self, config: PhysicsConfig) -> Self {
+        config.assert_valid();
+        self.config = config;
+        self
+    }
+
+    pub fn with_integrator(mut self, integrator: PhysicsIntegrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+}
+
+impl<S: States, P: Broadphase + Clone> Plugin for PhysicsPlugin<S, P> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PhysicsTick>();
+        app.add_event::<Impulse>();
+        app.insert_resource(self.config);
+        app.insert_resource(self.integrator);
+        app.init_resource::<PhysicsTimer>();
+        app.init_resource::<PhysicsTime>();
+        app.init_resource::<Gravity>();
+        app.insert_resource(CollisionBroadphase::<RigidBody>::new(
+            self.broadphase.clone(),
+        ));
+
+        let states = self.states.clone();
+        let in_physics_state = move |state: Option<Res<State<S>>>| {
+            state.is_some_and(|state| states.contains(state.get()))
+        };
+        let sets = [
+            PhysicsSet::Clock,
+            PhysicsSet::Forces,
+            PhysicsSet::Integrate,
+            PhysicsSet::Collide,
+            PhysicsSet::Interpolate,
+        ];
+        for set in sets {
+            app.configure_sets(Update, set.run_if(in_physics_state.clone()));
+        }
+        app.configure_sets(
+            Update,
+            (
+                PhysicsSet::Clock,
+                PhysicsSet::Forces,
+                PhysicsSet::Integrate,
+                PhysicsSet::Collide,
+                PhysicsSet::Interpolate,
+            )
+                .chain(),
+        );
+
+        app.add_systems(
+            Update,
+            (
+                physics_clock,
+                restore_physics_transforms,
+                crate::record_sweep_start,
+                follow_camera::<RigidBody>,
+            )
+                .chain()
+                .in_set(PhysicsSet::Clock),
+        );
+        app.add_systems(Update, sum_impulses.in_set(PhysicsSet::Forces));
+        app.add_systems(Update, apply_velocity.in_set(PhysicsSet::Integrate));
+        app.add_systems(
+            Update,
+            interpolate_physics_transforms.in_set(PhysicsSet::Interpolate),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::state::app::StatesPlugin;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Default, States)]
+    enum TestState {
+        #[default]
+        Menu,
+        Playing,
+    }
+
+    #[derive(Resource)]
+    struct Pusher(Entity);
+
+    fn push(pusher: Res<Pusher>, mut impulse: EventWriter<Impulse>) {
+        impulse.send(Impulse::add(pusher.0, Vec3::X));
+    }
+
+    fn test_app(start: TestState) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_state(start)
+            .add_plugins(PhysicsPlugin::new([TestState::Playing]))
+            .add_systems(Update, push.before(PhysicsSet::Forces));
+        let entity = app.world_mut().spawn(Velocity::default()).id();
+        app.insert_resource(Pusher(entity));
+        (app, entity)
+    }
+
+    #[test]
+    fn test_impulse_applies_same_frame() {
+        let (mut app, entity) = test_app(TestState::Playing);
+        app.update();
+        let velocity = app.world().get::<Velocity>(entity).unwrap();
+        assert_eq!(velocity.0, Vec3::X);
+    }
+
+    #[test]
+    #[should_panic(expected = "tick rate must be positive")]
+    fn test_config_needs_a_positive_tick_rate() {
+        PhysicsPlugin::new([TestState::Playing]).with_config(PhysicsConfig {
+            tick_rate: -30.0,
+            max_catch_up_ticks: 5,
+        });
+    }
+
+    #[test]
+    fn test_physics_only_runs_in_chosen_states() {
+        let (mut app, entity) = test_app(TestState::Menu);
+        app.update();
+        let velocity = app.world().get::<Velocity>(entity).unwrap();
+        assert_eq!(velocity.0, Vec3::ZERO);
+    }
+}
@@ -0,0 +1,264 @@
+use super::{Mass, Velocity};
+use crate::{
+    AxisAlignedBoundingBox, Broadphase, ColliderOffset, ColliderShape, Rect2D,
+    Sensor,
+};
+use bevy::{ecs::query::QueryData, prelude::*};
+
+/// Opts an entity with an [`AxisAlignedBoundingBox`] into collision
+/// response. Entities that are also a [`Sensor`] are left out.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RigidBody {
+    /// Pushed around by collisions
+    Dynamic,
+    /// Moves by its own [`Velocity`] but is never pushed back, like a
+    /// moving platform
+    Kinematic,
+    /// Never moves
+    Static,
+}
+
+/// Bounciness, from 0 (no bounce) to 1 (perfectly elastic). When two
+/// bodies meet, the bouncier of the two wins.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Restitution(pub f32);
+
+/// How much sliding contact slows bodies down. When two bodies meet,
+/// the geometric mean of the two is used.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Friction(pub f32);
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct RigidBodyQuery {
+    entity: Entity,
+    body: &'static RigidBody,
+    bbox: &'static AxisAlignedBoundingBox,
+    offset: Option<&'static ColliderOffset>,
+    transform: &'static mut Transform,
+    velocity: Option<&'static mut Velocity>,
+    mass: Option<&'static Mass>,
+    restitution: Option<&'static Restitution>,
+    friction: Option<&'static Friction>,
+}
+
+impl RigidBodyQueryItem<'_> {
+    fn inverse_mass(&self) -> f32 {
+        match self.body {
+            RigidBody::Dynamic => self.mass.map_or(1.0, Mass::inverse),
+            _ => 0.0,
+        }
+    }
+
+    /// The box in the world, moved by any [`ColliderOffset`] and
+    /// scaled with the entity.
+    fn rect(&self) -> Rect2D {
+        let offset = self.offset.map_or(Vec2::ZERO, |offset| offset.0);
+        ColliderShape::Aabb(*self.bbox)
+            .place(&self.transform, offset)
+            .bounds()
+    }
+
+    fn velocity(&self) -> Vec2 {
+        self.velocity
+            .as_ref()
+            .map_or(Vec2::ZERO, |velocity| velocity.0.truncate())
+    }
+
+    fn push(&mut self, offset: Vec2, impulse: Vec2) {
+        if *self.body != RigidBody::Dynamic {
+            return;
+        }
+        self.transform.translation += offset.extend(0.0);
+        if let Some(velocity) = &mut self.velocity {
+            velocity.0 += impulse.extend(0.0);
+        }
+    }
+}
+
+/// Pushes overlapping rigid bodies apart along the axis of least
+/// penetration, then bounces and slows them according to their
+/// [`Restitution`] and [`Friction`]. Runs once per physics tick, from
+/// [`apply_velocity`](super::apply_velocity), with candidate pairs
+/// found by `broadphase`.
+pub(crate) fn resolve_contacts(
+    bodies: &mut Query<RigidBodyQuery, Without<Sensor>>,
+    broadphase: &mut dyn Broadphase,
+) {
+    let (entities, rects): (Vec<Entity>, Vec<Rect2D>) = bodies
+        .iter_mut()
+        .map(|body| (body.entity, body.rect()))
+        .unzip();
+    broadphase.build(&rects);
+    let mut pairs = Vec::new();
+    broadphase.pairs(&mut pairs);
+    for (index_a, index_b) in pairs {
+        if let Ok([mut a, mut b]) =
+            bodies.get_many_mut([entities[index_a], entities[index_b]])
+        {
+            resolve_pair(&mut a, &mut b);
+        }
+    }
+}
+
+fn resolve_pair(a: &mut RigidBodyQueryItem, b: &mut RigidBodyQueryItem) {
+    let inverse_a = a.inverse_mass();
+    let inverse_b = b.inverse_mass();
+    let total_inverse = inverse_a + inverse_b;
+    if total_inverse == 0.0 {
+        return;
+    }
+
+    // Earlier pairs this tick may already have pushed the two apart
+    let rect_a = a.rect();
+    let rect_b = b.rect();
+    let Some(overlap) = rect_a.overlap(&rect_b) else {
+        return;
+    };
+
+    // The normal points from a to b
+    let between = rect_b.center() - rect_a.center();
+    let (normal, depth) = if overlap.x < overlap.y {
+        (Vec2::new(between.x.signum(), 0.0), overlap.x)
+    } else {
+        (Vec2::new(0.0, between.y.signum()), overlap.y)
+    };
+    let separation = normal * depth / total_inverse;
+    a.push(-separation * inverse_a, Vec2::ZERO);
+    b.push(separation * inverse_b, Vec2::ZERO);
+
+    let relative = b.velocity() - a.velocity();
+    let closing = relative.dot(normal);
+    if closing >= 0.0 {
+        return;
+    }
+    let restitution = a
+        .restitution
+        .map_or(0.0, |r| r.0)
+        .max(b.restitution.map_or(0.0, |r| r.0));
+    let j = -(1.0 + restitution) * closing / total_inverse;
+
+    let tangent = (relative - normal * closing).normalize_or_zero();
+    let friction = (a.friction.map_or(0.0, |f| f.0)
+        * b.friction.map_or(0.0, |f| f.0))
+    .sqrt();
+    let jt = (-relative.dot(tangent) / total_inverse)
+        .clamp(-j * friction, j * friction);
+
+    let impulse = normal * j + tangent * jt;
+    a.push(Vec2::ZERO, -impulse * inverse_a);
+    b.push(Vec2::ZERO, impulse * inverse_b);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::QuadTreeBroadphase;
+    use bevy::ecs::system::RunSystemOnce;
+
+    fn spawn_box(
+        world: &mut World,
+        body: RigidBody,
+        x: f32,
+        y: f32,
+        velocity: Vec3,
+    ) -> Entity {
+        world
+            .spawn((
+                body,
+                AxisAlignedBoundingBox::new(10.0, 10.0),
+                Transform::from_xyz(x, y, 0.0),
+                Velocity(velocity),
+            ))
+            .id()
+    }
+
+    fn resolve(world: &mut World) {
+        world
+            .run_system_once(
+                |mut bodies: Query<RigidBodyQuery, Without<Sensor>>| {
+                    let mut broadphase = QuadTreeBroadphase::default();
+                    resolve_contacts(&mut bodies, &mut broadphase);
+                },
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_dynamic_bodies_separate_and_bounce() {
+        let mut world = World::new();
+        let a = spawn_box(&mut world, RigidBody::Dynamic, 0.0, 0.0, Vec3::X);
+        let b = spawn_box(&mut world, RigidBody::Dynamic, 8.0, 1.0, -Vec3::X);
+        world.entity_mut(a).insert(Restitution(1.0));
+        resolve(&mut world);
+
+        let x = |e| world.get::<Transform>(e).unwrap().translation.x;
+        assert_eq!(x(a), -1.0);
+        assert_eq!(x(b), 9.0);
+        assert_eq!(world.get::<Velocity>(a).unwrap().0, -Vec3::X);
+        assert_eq!(world.get::<Velocity>(b).unwrap().0, Vec3::X);
+    }
+
+    #[test]
+    fn test_static_bodies_do_not_move() {
+        let mut world = World::new();
+        let ground =
+            spawn_box(&mut world, RigidBody::Static, 0.0, 0.0, Vec3::ZERO);
+        let ball = spawn_box(
+            &mut world,
+            RigidBody::Dynamic,
+            0.0,
+            7.0,
+            Vec3::new(0.0, -5.0, 0.0),
+        );
+        resolve(&mut world);
+
+        let ground_at = world.get::<Transform>(ground).unwrap().translation;
+        let ball_at = world.get::<Transform>(ball).unwrap().translation;
+        assert_eq!(ground_at, Vec3::ZERO);
+        assert_eq!(ball_at.y, 10.0);
+        // No restitution, so it comes to rest on the ground
+        assert_eq!(world.get::<Velocity>(ball).unwrap().0, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_sensors_do_not_push() {
+        let mut world = World::new();
+        let zone =
+            spawn_box(&mut world, RigidBody::Static, 0.0, 0.0, Vec3::ZERO);
+        world.entity_mut(zone).insert(Sensor);
+        let ball = spawn_box(
+            &mut world,
+            RigidBody::Dynamic,
+            0.0,
+            7.0,
+            Vec3::new(0.0, -5.0, 0.0),
+        );
+        resolve(&mut world);
+
+        let ball_at = world.get::<Transform>(ball).unwrap().translation;
+        assert_eq!(ball_at.y, 7.0);
+        assert_eq!(world.get::<Velocity>(ball).unwrap().0.y, -5.0);
+    }
+
+    #[test]
+    fn test_friction_slows_sliding() {
+        let mut world = World::new();
+        let ground =
+            spawn_box(&mut world, RigidBody::Static, 0.0, 0.0, Vec3::ZERO);
+        let slider = spawn_box(
+            &mut world,
+            RigidBody::Dynamic,
+            0.0,
+            9.0,
+            Vec3::new(10.0, -2.0, 0.0),
+        );
+        world.entity_mut(ground).insert(Friction(1.0));
+        world.entity_mut(slider).insert(Friction(1.0));
+        resolve(&mut world);
+
+        // The normal impulse is 2, which caps friction at 2
+        let velocity = world.get::<Velocity>(slider).unwrap().0;
+        assert_eq!(velocity, Vec3::new(8.0, 0.0, 0.0));
+    }
+}
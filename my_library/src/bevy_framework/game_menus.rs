@@ -31,7 +31,7 @@ pub(crate) fn setup<T: PluginState>(
         _ => panic!("Unknown menu state"),
     };
 
-    commands.spawn(Camera2d::default()).insert(MenuElement);
+    commands.spawn(Camera2d).insert(MenuElement);
 
     commands.spawn((
         Sprite::from(menu_graphic),
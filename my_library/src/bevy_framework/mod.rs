@@ -60,8 +60,6 @@ impl<T: PluginState + Copy + Default> Plugin for GameStatePlugin<T> {
     fn build(&self, app: &mut App) {
         app.init_state::<T>();
         app.add_plugins(bevy_egui::EguiPlugin);
-        app.add_event::<PhysicsTick>();
-        app.add_event::<Impulse>();
 
         let start = MenuResource {
             menu_state: self.menu_state,
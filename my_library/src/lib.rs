@@ -13,11 +13,11 @@
 //! ### Random Number Generation
 //!
 //! * The `locking` feature enables interior mutability inside
-//! [`RandomNumberGenerator`],
-//! allowing it to be used as a resource (`Res<RandomNumberGenerator`)
-//! rather than requiring mutability (`ResMut<RandomNumberGenerator>`)
+//!   [`RandomNumberGenerator`],
+//!   allowing it to be used as a resource (`Res<RandomNumberGenerator`)
+//!   rather than requiring mutability (`ResMut<RandomNumberGenerator>`)
 //! * You can control which random number generation algorithm is used by
-//! specifying *one* of:
+//!   specifying *one* of:
 //!      * `xorshift` to use the XorShift algorithm.
 //!      * `pcg` to use the PCG algorithm.
 